@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use crate::flare_net::net::Command;
+use super::{RoomOps, TraceContext};
 
 #[derive(Default)]
 pub struct AppContext {
@@ -18,6 +19,9 @@ pub struct AppContext {
     language: Option<String>,
     conn_id: String,
     client_msg_id: String,
+    rooms: Option<Arc<dyn RoomOps>>,
+    route: Option<String>,
+    trace: Option<TraceContext>,
 }
 
 impl AppContext {
@@ -46,6 +50,16 @@ impl AppContext {
         self.language.clone()
     }
 
+    /// 业务路由
+    pub fn route(&self) -> Option<String> {
+        self.route.clone()
+    }
+
+    /// 当前消息的链路追踪上下文
+    pub fn trace(&self) -> Option<TraceContext> {
+        self.trace
+    }
+
     // 数据操作相关
     pub fn data(&self) -> &[u8] {
         &self.data
@@ -126,6 +140,9 @@ impl AppContext {
         self.language = None;
         self.conn_id = String::new();
         self.client_msg_id = String::new();
+        self.rooms = None;
+        self.route = None;
+        self.trace = None;
     }
 
     pub fn values(&self) -> &Arc<Mutex<HashMap<String, String>>> {
@@ -139,6 +156,11 @@ impl AppContext {
     pub fn client_msg_id(&self) -> String {
         self.client_msg_id.clone()
     }
+
+    /// 房间操作，仅服务端上下文可用
+    pub fn rooms(&self) -> Option<Arc<dyn RoomOps>> {
+        self.rooms.clone()
+    }
 }
 
 impl Clone for AppContext {
@@ -154,6 +176,9 @@ impl Clone for AppContext {
             language: self.language.clone(),
             conn_id: self.conn_id.clone(),
             client_msg_id: self.client_msg_id.clone(),
+            rooms: self.rooms.clone(),
+            route: self.route.clone(),
+            trace: self.trace,
         }
     }
 }
@@ -170,6 +195,9 @@ pub struct AppContextBuilder {
     client_id: Option<String>,
     client_msg_id: Option<String>,
     conn_id: Option<String>,
+    rooms: Option<Arc<dyn RoomOps>>,
+    route: Option<String>,
+    trace: Option<TraceContext>,
 }
 
 impl AppContextBuilder {
@@ -228,6 +256,22 @@ impl AppContextBuilder {
         self
     }
 
+    pub fn with_rooms(mut self, rooms: Arc<dyn RoomOps>) -> Self {
+        self.rooms = Some(rooms);
+        self
+    }
+
+    /// 设置业务路由，空字符串视为未设置
+    pub fn with_route(mut self, route: String) -> Self {
+        self.route = if route.is_empty() { None } else { Some(route) };
+        self
+    }
+
+    pub fn with_trace(mut self, trace: TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
     pub fn build(self) -> Result<AppContext> {
         Ok(AppContext {
             remote_addr: self.remote_addr.ok_or_else(|| anyhow::anyhow!("remote_addr is required"))?,
@@ -240,6 +284,9 @@ impl AppContextBuilder {
             language: self.language,
             conn_id: self.conn_id.unwrap_or_else(String::new),
             client_msg_id: self.client_msg_id.unwrap_or_else(String::new),
+            rooms: self.rooms,
+            route: self.route,
+            trace: self.trace,
         })
     }
 }
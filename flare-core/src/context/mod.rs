@@ -1,2 +1,6 @@
 mod context;
-pub use context::{AppContext, AppContextBuilder};
\ No newline at end of file
+mod room;
+mod trace;
+pub use context::{AppContext, AppContextBuilder};
+pub use room::RoomOps;
+pub use trace::{TraceContext, TRACEPARENT_KEY};
@@ -0,0 +1,16 @@
+use crate::error::Result;
+use crate::flare_net::net::Message;
+use async_trait::async_trait;
+
+/// 房间操作，由服务端注入到 `AppContext` 中
+#[async_trait]
+pub trait RoomOps: Send + Sync {
+    /// 连接加入房间
+    async fn join(&self, room_id: &str, conn_id: &str) -> Result<()>;
+    /// 连接离开房间
+    async fn leave(&self, room_id: &str, conn_id: &str) -> Result<()>;
+    /// 房间内的用户ID
+    async fn members(&self, room_id: &str) -> Vec<String>;
+    /// 向房间内所有连接发送消息，可排除发送者，返回成功发送的连接数
+    async fn send(&self, room_id: &str, msg: Message, exclude_user: Option<&str>) -> Result<usize>;
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+/// 传递 trace 上下文的 gRPC 元数据键
+pub const TRACEPARENT_KEY: &str = "traceparent";
+
+const VERSION: &str = "00";
+const FLAG_SAMPLED: u8 = 0x01;
+
+/// W3C Trace Context，格式为 `00-<trace_id>-<span_id>-<flags>`
+///
+/// 每条 IM 消息对应一个 span，调用下游 RPC 时派生子 span 并写入 `traceparent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    flags: u8,
+}
+
+impl TraceContext {
+    /// 新建 trace，默认采样
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: uuid::Uuid::new_v4().as_u128(),
+            span_id: new_span_id(),
+            flags: FLAG_SAMPLED,
+        }
+    }
+
+    /// 解析 `traceparent`，格式错误或 id 全为 0 时返回 None
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        // 00 版本只有四段，更高版本允许追加字段
+        if !is_hex(version, 2) || version == "ff" || (version == VERSION && parts.next().is_some()) {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(span_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        let ctx = Self {
+            trace_id: u128::from_str_radix(trace_id, 16).ok()?,
+            span_id: u64::from_str_radix(span_id, 16).ok()?,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        };
+        (ctx.trace_id != 0 && ctx.span_id != 0).then_some(ctx)
+    }
+
+    /// 派生子 span，保留 trace id 和采样标记
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: new_span_id(),
+            ..*self
+        }
+    }
+
+    pub fn trace_id(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    pub fn span_id(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    pub fn sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+
+    pub fn to_traceparent(&self) -> String {
+        format!("{}-{:032x}-{:016x}-{:02x}", VERSION, self.trace_id, self.span_id, self.flags)
+    }
+
+    /// 设为 span 的 OpenTelemetry 父上下文，导出的 span 接入调用方的链路
+    ///
+    /// 需要 subscriber 注册了 `tracing_opentelemetry` 层，否则不生效
+    #[cfg(feature = "opentelemetry")]
+    pub fn set_parent_of(&self, span: &tracing::Span) {
+        use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let remote = SpanContext::new(
+            TraceId::from(self.trace_id),
+            SpanId::from(self.span_id),
+            TraceFlags::new(self.flags),
+            true,
+            TraceState::default(),
+        );
+        let _ = span.set_parent(opentelemetry::Context::new().with_remote_span_context(remote));
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_traceparent())
+    }
+}
+
+fn new_span_id() -> u64 {
+    // v4 UUID 含版本位，低 64 位不会全为 0
+    uuid::Uuid::new_v4().as_u64_pair().1
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent() {
+        let value = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let ctx = TraceContext::parse(value).unwrap();
+        assert_eq!(ctx.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.span_id(), "00f067aa0ba902b7");
+        assert!(ctx.sampled());
+        assert_eq!(ctx.to_traceparent(), value);
+
+        let child = ctx.child();
+        assert_eq!(child.trace_id(), ctx.trace_id());
+        assert_ne!(child.span_id(), ctx.span_id());
+
+        assert!(TraceContext::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+        assert!(TraceContext::parse("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01").is_none());
+        assert!(TraceContext::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none());
+        assert!(TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-x").is_none());
+        assert!(TraceContext::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-x").is_some_and(|c| !c.sampled()));
+
+        let root = TraceContext::new_root();
+        assert_eq!(TraceContext::parse(&root.to_traceparent()), Some(root));
+    }
+}
@@ -88,6 +88,10 @@ pub enum FlareErr {
     #[error("service not found: {0}")]
     ServiceNotFound(String),
 
+    // 限流
+    #[error("rate limited: {0}")]
+    RateLimited(String),
+
 }
 // 推送到客户端产生错误
 pub struct FlareError {
@@ -100,15 +104,30 @@ impl FlareErr {
     pub fn code(&self) -> ResCode {
         match self {
             FlareErr::Error(_) => ResCode::UnknownCode,
+            FlareErr::InvalidParams(_) => ResCode::InvalidParams,
             FlareErr::ConnectionError(_) => ResCode::ConnectionError,
             FlareErr::BusinessError(_) => ResCode::BusinessError,
             FlareErr::ArgsError(_) => ResCode::ArgsError,
-            FlareErr::InvalidParams(_) => ResCode::InvalidParams,
             FlareErr::ConnectionClosed => ResCode::ConnectionClosed,
             FlareErr::ConnectionNotFound => ResCode::ConnectionNotFound,
             FlareErr::DecodeError(_) => ResCode::DecodeError,
             FlareErr::EncodeError(_) => ResCode::EncodeError,
-            _ => ResCode::UnknownCode,
+            FlareErr::WebSocketError(_) => ResCode::WebsocketError,
+            FlareErr::InvalidMessageType => ResCode::InvalidMessageType,
+            FlareErr::ProtocolError(_) => ResCode::ProtocolError,
+            FlareErr::AuthError(_) => ResCode::AuthError,
+            FlareErr::Other(_) => ResCode::UnknownCode,
+            FlareErr::NotFondHandler => ResCode::NotFoundHandler,
+            FlareErr::PushToClientErr(_) => ResCode::PushToClientError,
+            FlareErr::SendMsgErr(_, _) => ResCode::SendMessageError,
+            FlareErr::InvalidCommand(_) => ResCode::InvalidCommand,
+            FlareErr::Unauthorized(_) => ResCode::Unauthorized,
+            FlareErr::InternalError(_) => ResCode::InternalError,
+            FlareErr::InvalidState(_) => ResCode::InvalidState,
+            FlareErr::Timeout(_) => ResCode::Timeout,
+            FlareErr::ResourceError(_) => ResCode::ResourceError,
+            FlareErr::ServiceNotFound(_) => ResCode::ServiceNotFound,
+            FlareErr::RateLimited(_) => ResCode::RateLimited,
         }
     }
 
@@ -158,6 +177,10 @@ impl FlareErr {
         FlareErr::ServiceNotFound(msg.into())
     }
 
+    pub fn rate_limited(msg: impl Into<String>) -> Self {
+        FlareErr::RateLimited(msg.into())
+    }
+
     pub fn connection_error(msg: impl Into<String>) -> Self {
         FlareErr::ConnectionError(msg.into())
     }
@@ -188,17 +211,6 @@ impl From<tungstenite::Error> for FlareErr {
 
 impl From<FlareErr> for ResCode {
     fn from(err: FlareErr) -> Self {
-        match err {
-            FlareErr::Error(_) => ResCode::UnknownCode,
-            FlareErr::ConnectionError(_) => ResCode::ConnectionError,
-            FlareErr::BusinessError(_) => ResCode::BusinessError,
-            FlareErr::ArgsError(_) => ResCode::ArgsError,
-            FlareErr::InvalidParams(_) => ResCode::InvalidParams,
-            FlareErr::ConnectionClosed => ResCode::ConnectionClosed,
-            FlareErr::ConnectionNotFound => ResCode::ConnectionNotFound,
-            FlareErr::DecodeError(_) => ResCode::DecodeError,
-            FlareErr::EncodeError(_) => ResCode::EncodeError,
-            _ => ResCode::UnknownCode,
-        }
+        err.code()
     }
 }
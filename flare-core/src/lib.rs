@@ -1,4 +1,6 @@
 pub mod context;
 pub mod error;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 mod net;
 pub use net::flare_net;
\ No newline at end of file
@@ -0,0 +1,23 @@
+mod server;
+
+pub use prometheus;
+pub use server::serve;
+
+use prometheus::{Encoder, Registry, TextEncoder};
+
+/// 指标注册表，各模块的指标都注册到 Prometheus 的默认注册表
+pub fn registry() -> &'static Registry {
+    prometheus::default_registry()
+}
+
+/// 以 Prometheus 文本格式导出所有指标
+pub fn encode() -> String {
+    let mut buf = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&registry().gather(), &mut buf) {
+        log::error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buf).unwrap_or_default()
+}
+
+/// 延迟直方图的分桶，单位秒
+pub const LATENCY_BUCKETS: &[f64] = &[0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
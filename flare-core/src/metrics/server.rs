@@ -0,0 +1,107 @@
+use crate::error::{FlareErr, Result};
+use crate::metrics::encode;
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::header::CONTENT_TYPE;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use log::{debug, info};
+use prometheus::TEXT_FORMAT;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+
+/// 指标导出路径
+pub const METRICS_PATH: &str = "/metrics";
+
+/// 在 `addr` 上提供 `GET /metrics`，`signal` 完成时退出
+pub async fn serve(addr: SocketAddr, signal: impl Future<Output = ()>) -> Result<()> {
+    let listener = TcpListener::bind(addr).await
+        .map_err(|e| FlareErr::connection_error(format!("Failed to bind metrics address {}: {}", addr, e)))?;
+    info!("Metrics endpoint listening on http://{}{}", addr, METRICS_PATH);
+    tokio::pin!(signal);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        debug!("Failed to accept metrics connection: {}", e);
+                        continue;
+                    }
+                };
+                tokio::spawn(async move {
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service_fn(handle))
+                        .await
+                    {
+                        debug!("Metrics connection error: {}", e);
+                    }
+                });
+            }
+            _ = &mut signal => return Ok(()),
+        }
+    }
+}
+
+async fn handle(req: Request<Incoming>) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+    let resp = if req.method() == Method::GET && req.uri().path() == METRICS_PATH {
+        Response::builder()
+            .header(CONTENT_TYPE, TEXT_FORMAT)
+            .body(Full::new(Bytes::from(encode())))
+    } else {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Full::new(Bytes::from_static(b"not found")))
+    };
+    Ok(resp.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{register_int_counter, IntCounter};
+    use std::sync::LazyLock;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    static TEST_COUNTER: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter!("flare_test_requests_total", "test counter").unwrap()
+    });
+
+    async fn get(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).as_bytes()).await.unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        resp
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        TEST_COUNTER.inc_by(3);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(addr, async { let _ = stop_rx.await; }));
+        for _ in 0..50 {
+            if TcpStream::connect(addr).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let resp = get(addr.port(), METRICS_PATH).await;
+        assert!(resp.starts_with("HTTP/1.1 200"));
+        assert!(resp.contains("flare_test_requests_total 3"));
+        assert!(get(addr.port(), "/other").await.starts_with("HTTP/1.1 404"));
+
+        let _ = stop_tx.send(());
+        server.await.unwrap().unwrap();
+    }
+}
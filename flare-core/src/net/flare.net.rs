@@ -11,6 +11,24 @@ pub struct Message {
     /// 客户端消息id
     #[prost(string, tag = "3")]
     pub client_id: ::prost::alloc::string::String,
+    /// 服务端消息id，客户端收到后需回复 CLIENT_ACK
+    #[prost(string, tag = "4")]
+    pub server_msg_id: ::prost::alloc::string::String,
+    /// 连接内序列号，服务端推送时从 1 递增
+    #[prost(uint64, tag = "5")]
+    pub seq: u64,
+    /// 会话id
+    #[prost(string, tag = "6")]
+    pub conversation_id: ::prost::alloc::string::String,
+    /// 会话内序列号，由业务方分配并单调递增
+    #[prost(uint64, tag = "7")]
+    pub conversation_seq: u64,
+    /// 业务路由，服务端按路由分发到注册的处理器
+    #[prost(string, tag = "8")]
+    pub route: ::prost::alloc::string::String,
+    /// W3C 链路追踪上下文，为空时服务端生成新的 trace
+    #[prost(string, tag = "9")]
+    pub traceparent: ::prost::alloc::string::String,
 }
 /// 响应消息
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -37,6 +55,9 @@ pub struct LoginReq {
     /// token
     #[prost(string, tag = "4")]
     pub token: ::prost::alloc::string::String,
+    /// 会话恢复令牌，有效时无需 token
+    #[prost(string, tag = "5")]
+    pub resume_token: ::prost::alloc::string::String,
 }
 /// 登录响应
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -47,6 +68,94 @@ pub struct LoginResp {
     /// 语言
     #[prost(string, tag = "2")]
     pub language: ::prost::alloc::string::String,
+    /// 会话恢复令牌，断线后在恢复窗口内可免认证恢复会话
+    #[prost(string, tag = "3")]
+    pub resume_token: ::prost::alloc::string::String,
+    /// 前台心跳间隔，0 表示使用客户端配置
+    #[prost(uint32, tag = "4")]
+    pub heartbeat_interval_ms: u32,
+    /// 后台心跳间隔，0 表示使用客户端配置
+    #[prost(uint32, tag = "5")]
+    pub background_heartbeat_interval_ms: u32,
+}
+/// 拉取消息请求
+/// conversation_id 不为空时补齐会话中缺失的序列号区间 \[from_seq, to_seq\]
+/// conversation_id 为空时按游标分页拉取离线消息，cursor 之前的消息视为已确认
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PullReq {
+    /// 会话id
+    #[prost(string, tag = "1")]
+    pub conversation_id: ::prost::alloc::string::String,
+    /// 起始序列号
+    #[prost(uint64, tag = "2")]
+    pub from_seq: u64,
+    /// 结束序列号
+    #[prost(uint64, tag = "3")]
+    pub to_seq: u64,
+    /// 离线消息游标
+    #[prost(uint64, tag = "4")]
+    pub cursor: u64,
+    /// 每页数量，0 使用服务端默认值
+    #[prost(uint32, tag = "5")]
+    pub limit: u32,
+}
+/// 拉取离线消息响应
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PullResp {
+    /// 消息列表
+    #[prost(message, repeated, tag = "1")]
+    pub messages: ::prost::alloc::vec::Vec<Message>,
+    /// 下一页游标
+    #[prost(uint64, tag = "2")]
+    pub next_cursor: u64,
+    /// 是否还有更多
+    #[prost(bool, tag = "3")]
+    pub has_more: bool,
+}
+/// 用户在线状态，变化时以 SERVER_PUSH_NOTICE 推送给订阅者
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Presence {
+    /// 用户id
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    /// 在线状态
+    #[prost(enumeration = "PresenceState", tag = "2")]
+    pub state: i32,
+    /// 最后在线时间，毫秒时间戳
+    #[prost(int64, tag = "3")]
+    pub last_seen: i64,
+    /// 在线的平台
+    #[prost(enumeration = "Platform", repeated, tag = "4")]
+    pub platforms: ::prost::alloc::vec::Vec<i32>,
+}
+/// 订阅在线状态请求
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PresenceSubReq {
+    /// 订阅的用户
+    #[prost(string, repeated, tag = "1")]
+    pub user_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// 取消订阅
+    #[prost(bool, tag = "2")]
+    pub unsubscribe: bool,
+}
+/// 订阅在线状态响应，包含订阅用户的当前状态
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PresenceList {
+    #[prost(message, repeated, tag = "1")]
+    pub presences: ::prost::alloc::vec::Vec<Presence>,
+}
+/// 服务端主动关闭连接的通知，随 CLOSE 命令下发
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CloseNotice {
+    /// 关闭原因
+    #[prost(string, tag = "1")]
+    pub reason: ::prost::alloc::string::String,
+    /// 是否建议客户端重连
+    #[prost(bool, tag = "2")]
+    pub reconnect: bool,
+    /// 建议的重连延迟，毫秒
+    #[prost(uint32, tag = "3")]
+    pub retry_after_ms: u32,
 }
 /// 设备平台
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -128,6 +237,8 @@ pub enum Command {
     KickOnline = 7,
     /// 链接关闭
     Close = 8,
+    /// 订阅在线状态
+    SubscribePresence = 9,
     /// 客户端命令 (10-29)
     ///
     /// 客户端发送消息
@@ -169,6 +280,7 @@ impl Command {
             Self::SetLanguage => "SET_LANGUAGE",
             Self::KickOnline => "KICK_ONLINE",
             Self::Close => "CLOSE",
+            Self::SubscribePresence => "SUBSCRIBE_PRESENCE",
             Self::ClientSendMessage => "CLIENT_SEND_MESSAGE",
             Self::ClientPullMessage => "CLIENT_PULL_MESSAGE",
             Self::ClientRequest => "CLIENT_REQUEST",
@@ -193,6 +305,7 @@ impl Command {
             "SET_LANGUAGE" => Some(Self::SetLanguage),
             "KICK_ONLINE" => Some(Self::KickOnline),
             "CLOSE" => Some(Self::Close),
+            "SUBSCRIBE_PRESENCE" => Some(Self::SubscribePresence),
             "CLIENT_SEND_MESSAGE" => Some(Self::ClientSendMessage),
             "CLIENT_PULL_MESSAGE" => Some(Self::ClientPullMessage),
             "CLIENT_REQUEST" => Some(Self::ClientRequest),
@@ -257,6 +370,10 @@ pub enum ResCode {
     ConnectionError = 21,
     /// 参数错误
     ArgsError = 22,
+    /// 请求过于频繁
+    RateLimited = 23,
+    /// 服务不存在
+    ServiceNotFound = 24,
 }
 impl ResCode {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -288,6 +405,8 @@ impl ResCode {
             Self::ResourceError => "RESOURCE_ERROR",
             Self::ConnectionError => "CONNECTION_ERROR",
             Self::ArgsError => "ARGS_ERROR",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::ServiceNotFound => "SERVICE_NOT_FOUND",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -316,6 +435,41 @@ impl ResCode {
             "RESOURCE_ERROR" => Some(Self::ResourceError),
             "CONNECTION_ERROR" => Some(Self::ConnectionError),
             "ARGS_ERROR" => Some(Self::ArgsError),
+            "RATE_LIMITED" => Some(Self::RateLimited),
+            "SERVICE_NOT_FOUND" => Some(Self::ServiceNotFound),
+            _ => None,
+        }
+    }
+}
+/// 在线状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum PresenceState {
+    /// 离线
+    Offline = 0,
+    /// 在线
+    Online = 1,
+    /// 所有连接都在后台运行
+    Background = 2,
+}
+impl PresenceState {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Offline => "OFFLINE",
+            Self::Online => "ONLINE",
+            Self::Background => "BACKGROUND",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "OFFLINE" => Some(Self::Offline),
+            "ONLINE" => Some(Self::Online),
+            "BACKGROUND" => Some(Self::Background),
             _ => None,
         }
     }
@@ -19,9 +19,10 @@ async fn main() -> anyhow::Result<()> {
     let protocol = match args.get(1).map(|s| s.as_str()) {
         Some("ws") => Protocol::WebSocket,
         Some("quic") => Protocol::Quic,
+        Some("tcp") => Protocol::Tcp,
         Some("auto") => Protocol::Auto,
         _ => {
-            println!("Usage: {} [ws|quic|auto]", args[0]);
+            println!("Usage: {} [ws|quic|tcp|auto]", args[0]);
             println!("Defaulting to WebSocket");
             Protocol::WebSocket
         }
@@ -55,6 +56,9 @@ async fn main() -> anyhow::Result<()> {
                 .quic_is_test(true)
                 .use_quic();
         }
+        Protocol::Tcp => {
+            builder = builder.tcp_addr("127.0.0.1:8082").use_tcp();
+        }
         Protocol::Auto => {
             builder = builder
                 .ws_url("ws://127.0.0.1:8080")
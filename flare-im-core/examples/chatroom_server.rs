@@ -12,6 +12,8 @@ use log::{error, info};
 use prost::Message;
 use flare_core::flare_net::net::{Command, Message as ProtoMessage, ResCode, Response};
 
+const ROOM_ID: &str = "lobby";
+
 // 聊天室消息处理器
 struct ChatHandler;
 
@@ -34,6 +36,13 @@ impl ServerHandler for ChatHandler {
                 ..Default::default()
             };
 
+            // 发消息的连接加入聊天室，并转发给房间内的其他用户
+            if let Some(rooms) = ctx.rooms() {
+                rooms.join(ROOM_ID, &ctx.conn_id()).await?;
+                let sender = ctx.user_id();
+                rooms.send(ROOM_ID, broadcast_msg.clone(), sender.as_deref()).await?;
+            }
+
             response.code = ResCode::Success as i32;
             response.message = "Message sent".to_string();
             response.data = broadcast_msg.encode_to_vec();
@@ -70,6 +79,7 @@ async fn main() -> anyhow::Result<()> {
     let server = FlareServer::builder()
         .ws_addr("127.0.0.1:8080")
         .quic_addr("127.0.0.1:8081")
+        .tcp_addr("127.0.0.1:8082")
         .quic_server_name("hugo.im.quic.cn")
         .quic_cert_path("certs/cert.pem")
         .quic_key_path("certs/key.pem")
@@ -1,13 +1,15 @@
 use crate::client::config::ClientConfig;
 use crate::client::handlers::ClientMessageHandler;
 use crate::client::message_handler::DefMessageHandler;
+use crate::client::outbox::Outbox;
+use crate::client::sequence::SeqTracker;
 use crate::client::sys_handler::DefClientSystemHandler;
 use flare_core::error::FlareErr;
 use flare_core::error::Result;
 use crate::connections::Connection;
 use log::{debug, error, warn};
 use prost::Message as ProstMessage;
-use flare_core::flare_net::net::LoginReq;
+use flare_core::flare_net::net::{LoginReq, LoginResp, Presence, PresenceList, PresenceSubReq, PullReq, PullResp, ResCode};
 use flare_core::flare_net::net::{Command, Message as ProtoMessage, Response};
 use std::collections::HashMap;
 use std::fmt;
@@ -16,14 +18,40 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::oneshot;
-use tokio::sync::{mpsc, Mutex};
-use tokio::time::{interval, sleep, Instant};
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::time::{sleep, Instant};
 use uuid;
 
 const PING_INTERVAL: Duration = Duration::from_secs(30);
 const PONG_TIMEOUT: Duration = Duration::from_secs(10);
 const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
 const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+/// 登录请求的 client_id，用于识别登录响应
+const LOGIN_REQUEST_ID: &str = "login";
+/// 等待登录响应的超时时间
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 服务端在登录响应中下发的心跳间隔
+#[derive(Clone, Copy, Debug, Default)]
+struct Heartbeat {
+    foreground: Option<Duration>,
+    background: Option<Duration>,
+    in_background: bool,
+}
+
+impl Heartbeat {
+    fn update(&mut self, resp: &LoginResp) {
+        let ms = |v: u32| (v > 0).then(|| Duration::from_millis(v as u64));
+        self.foreground = ms(resp.heartbeat_interval_ms);
+        self.background = ms(resp.background_heartbeat_interval_ms);
+    }
+
+    /// 当前的心跳间隔，服务端未下发时使用本地配置
+    fn interval(&self, fallback: Duration) -> Duration {
+        let interval = if self.in_background { self.background } else { self.foreground };
+        interval.unwrap_or(fallback)
+    }
+}
 
 #[derive(Clone, Debug)]
 pub enum ClientState {
@@ -61,6 +89,18 @@ where
     last_pong: Arc<Mutex<Instant>>,
     is_running: Arc<Mutex<bool>>,
     pending_requests: Arc<Mutex<HashMap<String, oneshot::Sender<Response>>>>,
+    outbox: Outbox,
+    send_lock: Arc<Mutex<()>>,
+    seq_tracker: Arc<Mutex<SeqTracker>>,
+    // 服务端签发的会话恢复令牌，重连时免认证恢复会话
+    resume_token: Arc<Mutex<String>>,
+    heartbeat: Arc<Mutex<Heartbeat>>,
+    // 前后台切换时通知心跳任务立即发送心跳并重新计时
+    heartbeat_changed: Arc<watch::Sender<()>>,
+    // 等待中的登录响应
+    login_waiter: Arc<Mutex<Option<oneshot::Sender<LoginResp>>>>,
+    // 进行中的缺口补拉，按请求的 client_id 索引
+    gap_pulls: Arc<Mutex<HashMap<String, PullReq>>>,
 }
 
 impl<F> Client<F>
@@ -78,6 +118,13 @@ where
         let is_running = Arc::new(Mutex::new(true));
         let last_pong = Arc::new(Mutex::new(Instant::now()));
         let pending_requests = Arc::new(Mutex::new(HashMap::new()));
+        let outbox = match &config.outbox_path {
+            Some(path) => Outbox::open(path).unwrap_or_else(|e| {
+                error!("Failed to open outbox, fallback to memory: {}", e);
+                Outbox::new()
+            }),
+            None => Outbox::new(),
+        };
 
         let client = Self {
             config: Arc::new(Mutex::new(config)),
@@ -89,6 +136,14 @@ where
             last_pong,
             is_running,
             pending_requests,
+            outbox,
+            send_lock: Arc::new(Mutex::new(())),
+            seq_tracker: Arc::new(Mutex::new(SeqTracker::new())),
+            resume_token: Arc::new(Mutex::new(String::new())),
+            heartbeat: Arc::new(Mutex::new(Heartbeat::default())),
+            heartbeat_changed: Arc::new(watch::Sender::new(())),
+            login_waiter: Arc::new(Mutex::new(None)),
+            gap_pulls: Arc::new(Mutex::new(HashMap::new())),
         };
 
         // 启动消息发送任务
@@ -106,6 +161,7 @@ where
         let connector = self.connector.lock().await;
         let new_conn = (connector)().await?;
         *self.conn.lock().await = Some(new_conn);
+        self.seq_tracker.lock().await.reset_connection();
 
         // 启动消息接收循环
         self.spawn_receiver();
@@ -115,9 +171,6 @@ where
 
         // 认证成功后启动心跳检测
         self.spawn_keepalive();
-        
-        // 更新状态
-        self.set_state(ClientState::Connected).await;
         Ok(())
     }
 
@@ -127,21 +180,9 @@ where
         while attempt < self.config.lock().await.max_reconnect_attempts {
             self.set_state(ClientState::Reconnecting { attempt }).await;
             
+            // connect 内已完成认证并启动心跳检测
             match self.connect().await {
-                Ok(()) => {
-                    // 重连成功后重新进行认证
-                    match self.authenticate().await {
-                        Ok(()) => {
-                            // 认证成功后重新启动心跳检测
-                            self.spawn_keepalive();
-                            return Ok(());
-                        }
-                        Err(e) => {
-                            error!("Authentication failed after reconnection: {}", e);
-                            attempt += 1;
-                        }
-                    }
-                }
+                Ok(()) => return Ok(()),
                 Err(e) => {
                     error!("Reconnection attempt {} failed: {}", attempt, e);
                     attempt += 1;
@@ -190,6 +231,7 @@ where
         let mut conn = self.conn.lock().await;
         *conn = Some(connection);
         drop(conn);
+        self.seq_tracker.lock().await.reset_connection();
 
         // 更新配置
         let mut config = self.config.lock().await;
@@ -208,25 +250,68 @@ where
         // 进行认证
         self.authenticate().await?;
 
-        // 设置状态为已连接
-        self.set_state(ClientState::Connected).await;
-
         // 等待连接就绪
         self.wait_ready(Duration::from_secs(5)).await?;
 
         Ok(())
     }
 
-    /// 发送消息
+    /// 发送消息，未认证时先放入发件箱，认证后按顺序重放
     pub async fn send(&self, msg: ProtoMessage) -> Result<()> {
         if !*self.is_running.lock().await {
             return Err(anyhow::anyhow!("Client is not running").into());
         }
+        let _guard = self.send_lock.lock().await;
+        // 发件箱非空时也要排队，避免新消息越过待重放的消息
+        if !matches!(self.get_state().await, ClientState::Authenticated) || !self.outbox.is_empty().await {
+            if !self.outbox.push(msg).await? {
+                debug!("Duplicate message ignored by outbox");
+            }
+            return Ok(());
+        }
+        self.send_now(msg).await
+    }
+
+    /// 当前连接最近收到的推送序列号
+    pub async fn last_seq(&self) -> u64 {
+        self.seq_tracker.lock().await.conn_seq()
+    }
+
+    /// 会话最近收到的序列号
+    pub async fn conversation_seq(&self, conversation_id: &str) -> u64 {
+        self.seq_tracker.lock().await.conversation_seq(conversation_id)
+    }
+
+    /// 发件箱中待发送的消息数量
+    pub async fn outbox_len(&self) -> usize {
+        self.outbox.len().await
+    }
+
+    async fn send_now(&self, msg: ProtoMessage) -> Result<()> {
         self.message_sender.send(msg).await
             .map_err(|e| anyhow::anyhow!("Failed to send message: {}", e))?;
         Ok(())
     }
 
+    /// 重放发件箱中的消息，未发出的消息放回发件箱
+    async fn flush_outbox(&self) -> Result<()> {
+        let _guard = self.send_lock.lock().await;
+        let msgs = self.outbox.drain().await?;
+        if !msgs.is_empty() {
+            debug!("Replaying {} messages from outbox", msgs.len());
+        }
+        let mut msgs = msgs.into_iter();
+        while let Some(msg) = msgs.next() {
+            if let Err(e) = self.send_now(msg.clone()).await {
+                let mut rest = vec![msg];
+                rest.extend(msgs);
+                self.outbox.requeue(rest).await?;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     /// 发送消息并等待响应
     pub async fn send_wait(&self, msg: ProtoMessage) -> Result<Response> {
         // 创建一个新的可变消息
@@ -256,6 +341,78 @@ where
             .map_err(|_| FlareErr::ConnectionError("Request timeout".to_string()))?
     }
 
+    /// 按路由发送业务请求，请求和响应自动编解码
+    pub async fn request<T: ProstMessage, R: ProstMessage + Default>(&self, route: &str, req: &T) -> Result<R> {
+        let resp = self.send_wait(ProtoMessage {
+            command: Command::ClientRequest as i32,
+            route: route.to_string(),
+            data: req.encode_to_vec(),
+            ..Default::default()
+        }).await?;
+        if resp.code != ResCode::Success as i32 {
+            return Err(FlareErr::BusinessError(resp.message));
+        }
+        Ok(R::decode(&resp.data[..])?)
+    }
+
+    /// 从游标处分页拉取离线消息，返回的 `next_cursor` 用于拉取下一页
+    pub async fn pull_offline(&self, cursor: u64, limit: u32) -> Result<PullResp> {
+        let resp = self.send_wait(ProtoMessage {
+            command: Command::ClientPullMessage as i32,
+            data: PullReq { cursor, limit, ..Default::default() }.encode_to_vec(),
+            ..Default::default()
+        }).await?;
+        if resp.code != ResCode::Success as i32 {
+            return Err(FlareErr::BusinessError(resp.message));
+        }
+        Ok(PullResp::decode(&resp.data[..])?)
+    }
+
+    /// 订阅用户的在线状态，返回当前状态，之后的变化以 `SERVER_PUSH_NOTICE` 推送
+    pub async fn subscribe_presence(&self, user_ids: Vec<String>) -> Result<Vec<Presence>> {
+        let list: PresenceList = self.presence_request(PresenceSubReq { user_ids, unsubscribe: false }).await?;
+        Ok(list.presences)
+    }
+
+    /// 取消订阅在线状态
+    pub async fn unsubscribe_presence(&self, user_ids: Vec<String>) -> Result<()> {
+        self.presence_request(PresenceSubReq { user_ids, unsubscribe: true }).await?;
+        Ok(())
+    }
+
+    async fn presence_request(&self, req: PresenceSubReq) -> Result<PresenceList> {
+        let resp = self.send_wait(ProtoMessage {
+            command: Command::SubscribePresence as i32,
+            data: req.encode_to_vec(),
+            ..Default::default()
+        }).await?;
+        if resp.code != ResCode::Success as i32 {
+            return Err(FlareErr::BusinessError(resp.message));
+        }
+        Ok(PresenceList::decode(&resp.data[..])?)
+    }
+
+    /// 通知服务端切换前后台，心跳间隔随之切换
+    pub async fn set_background(&self, background: bool) -> Result<()> {
+        let resp = self.send_wait(ProtoMessage {
+            command: Command::SetBackground as i32,
+            data: vec![background as u8],
+            ..Default::default()
+        }).await?;
+        if resp.code != ResCode::Success as i32 {
+            return Err(FlareErr::BusinessError(resp.message));
+        }
+        self.heartbeat.lock().await.in_background = background;
+        self.heartbeat_changed.send_replace(());
+        Ok(())
+    }
+
+    /// 当前的心跳间隔
+    pub async fn heartbeat_interval(&self) -> Duration {
+        let fallback = self.config.lock().await.ping_interval;
+        self.heartbeat.lock().await.interval(fallback)
+    }
+
     /// 获取当前状态
     pub async fn get_state(&self) -> ClientState {
         self.state.lock().await.clone()
@@ -303,7 +460,7 @@ where
         }
     }
 
-    // 认证相关
+    /// 发送登录请求并等待登录响应，登录成功后才重放发件箱
     async fn authenticate(&self) -> Result<()> {
         self.set_state(ClientState::Authenticating).await;
         let conf = self.config.lock().await;
@@ -312,16 +469,47 @@ where
             platform: conf.platform as i32,
             client_id: conf.client_id.clone(),
             token: conf.auth_token.clone(),
-            ..Default::default()
+            resume_token: self.resume_token.lock().await.clone(),
         };
+        drop(conf);
         let auth_msg = ProtoMessage {
             command: Command::Login as i32,
             data: req.encode_to_vec(),
+            client_id: LOGIN_REQUEST_ID.to_string(),
             ..Default::default()
         };
         
-        self.send(auth_msg).await?;
+        let (tx, rx) = oneshot::channel();
+        *self.login_waiter.lock().await = Some(tx);
+        // 登录消息不经过发件箱
+        self.send_now(auth_msg).await?;
+        let resp = match tokio::time::timeout(LOGIN_TIMEOUT, rx).await {
+            Ok(Ok(resp)) => Some(resp),
+            Ok(Err(_)) => None,
+            Err(_) => {
+                self.login_waiter.lock().await.take();
+                None
+            }
+        };
+        // 登录失败时消息留在发件箱，等待下次认证
+        if resp.is_none_or(|resp| resp.user_id.is_empty()) {
+            self.set_state(ClientState::Connected).await;
+            return Err(FlareErr::AuthError("Login failed".to_string()));
+        }
         self.set_state(ClientState::Authenticated).await;
+        self.flush_outbox().await?;
+        // 服务端的新连接默认在前台，重新认证后同步后台状态，超时视为恢复失败，不阻塞连接
+        if self.heartbeat.lock().await.in_background {
+            let restored = tokio::time::timeout(LOGIN_TIMEOUT, self.set_background(true))
+                .await
+                .map_err(|_| FlareErr::ConnectionError("Request timeout".to_string()))
+                .and_then(|r| r);
+            if let Err(e) = restored {
+                warn!("Failed to restore background state: {}", e);
+                self.heartbeat.lock().await.in_background = false;
+                self.heartbeat_changed.send_replace(());
+            }
+        }
         Ok(())
     }
 
@@ -341,6 +529,7 @@ where
     fn spawn_sender(&self, mut rx: mpsc::Receiver<ProtoMessage>) {
         let conn = self.conn.clone();
         let is_running = self.is_running.clone();
+        let outbox = self.outbox.clone();
         
         tokio::spawn(async move {
             let mut conn_ref = None;
@@ -361,7 +550,7 @@ where
                                 
                                 // 缓冲区满时立即发送
                                 if msg_buffer.len() >= 32 {
-                                    if let Err(e) = Self::flush_messages(&mut conn_ref, &conn, &mut msg_buffer).await {
+                                    if let Err(e) = Self::flush_messages(&mut conn_ref, &conn, &outbox, &mut msg_buffer).await {
                                         error!("Failed to flush messages: {}", e);
                                     }
                                 }
@@ -373,7 +562,7 @@ where
                     // 定时刷新缓冲区
                     _ = flush_timer.tick() => {
                         if !msg_buffer.is_empty() {
-                            if let Err(e) = Self::flush_messages(&mut conn_ref, &conn, &mut msg_buffer).await {
+                            if let Err(e) = Self::flush_messages(&mut conn_ref, &conn, &outbox, &mut msg_buffer).await {
                                 error!("Failed to flush messages: {}", e);
                             }
                         }
@@ -383,7 +572,7 @@ where
             
             // 退出前确保发送所有消息
             if !msg_buffer.is_empty() {
-                if let Err(e) = Self::flush_messages(&mut conn_ref, &conn, &mut msg_buffer).await {
+                if let Err(e) = Self::flush_messages(&mut conn_ref, &conn, &outbox, &mut msg_buffer).await {
                     error!("Failed to flush remaining messages: {}", e);
                 }
             }
@@ -396,6 +585,11 @@ where
         let is_running = self.is_running.clone();
         let last_pong = self.last_pong.clone();
         let pending_requests = self.pending_requests.clone();
+        let seq_tracker = self.seq_tracker.clone();
+        let resume_token = self.resume_token.clone();
+        let heartbeat = self.heartbeat.clone();
+        let login_waiter = self.login_waiter.clone();
+        let gap_pulls = self.gap_pulls.clone();
 
         tokio::spawn(async move {
             while *is_running.lock().await {
@@ -417,8 +611,25 @@ where
                                 }
                                 continue;
                             }
+                            // 登录响应，记录会话恢复令牌和心跳间隔
+                            if msg.command == Command::ServerResponse as i32 && msg.client_id == LOGIN_REQUEST_ID {
+                                let resp = LoginResp::decode(&msg.data[..]).unwrap_or_default();
+                                heartbeat.lock().await.update(&resp);
+                                // 令牌只能使用一次，失败的登录也要清掉旧令牌
+                                *resume_token.lock().await = resp.resume_token.clone();
+                                if let Some(tx) = login_waiter.lock().await.take() {
+                                    let _ = tx.send(resp);
+                                }
+                                continue;
+                            }
                             // 处理响应消息
                             if msg.command == Command::ServerResponse as i32 {
+                                // 缺口补拉的响应，补回的消息按推送处理
+                                let req = gap_pulls.lock().await.remove(&msg.client_id);
+                                if let Some(req) = req {
+                                    Self::handle_gap_pull(&conn_ref, &handler, &seq_tracker, &gap_pulls, req, &msg.data).await;
+                                    continue;
+                                }
                                 if let Ok(response) = Response::decode(&msg.data[..]) {
                                     // 检查是否有待处理的请求
                                     let mut pending = pending_requests.lock().await;
@@ -431,15 +642,12 @@ where
                                 continue;
                             }
 
-                            // 处理其他消息
-                            if let Ok(command) = Command::try_from(msg.command) {
-                                if let Err(e) = handler.handle_command(command, msg.data).await {
-                                    error!("Failed to handle command: {}", e);
-                                }
-                            }
+                            Self::dispatch(&conn_ref, &handler, &seq_tracker, &gap_pulls, msg).await;
                         }
                         Err(e) => {
                             error!("Failed to receive message: {}", e);
+                            // 连接断开时结束等待中的登录
+                            login_waiter.lock().await.take();
                             break;
                         }
                     }
@@ -449,21 +657,121 @@ where
         });
     }
 
+    /// 处理推送消息：检测序列号缺口、回复 ACK 并交给消息处理器
+    async fn dispatch(
+        conn: &Arc<Box<dyn Connection>>,
+        handler: &ClientMessageHandler<DefClientSystemHandler, DefMessageHandler>,
+        seq_tracker: &Mutex<SeqTracker>,
+        gap_pulls: &Mutex<HashMap<String, PullReq>>,
+        msg: ProtoMessage,
+    ) {
+        // 会话序列号出现缺口时补拉
+        let gap = seq_tracker.lock().await.observe(&msg);
+        if let Some(req) = gap {
+            debug!("Pull missing messages {}..={} of {}", req.from_seq, req.to_seq, req.conversation_id);
+            if let Err(e) = Self::pull(conn, gap_pulls, req).await {
+                error!("Failed to pull missing messages: {}", e);
+            }
+        }
+
+        // 可靠推送需回复 ACK
+        if !msg.server_msg_id.is_empty() {
+            if let Err(e) = conn.send(ProtoMessage {
+                command: Command::ClientAck as i32,
+                data: msg.server_msg_id.clone().into_bytes(),
+                ..Default::default()
+            }).await {
+                error!("Failed to send ack for {}: {}", msg.server_msg_id, e);
+            }
+        }
+
+        // 处理其他消息
+        if let Ok(command) = Command::try_from(msg.command) {
+            if let Err(e) = handler.handle_command(command, msg.data).await {
+                error!("Failed to handle command: {}", e);
+            }
+        }
+    }
+
+    /// 处理补拉响应，补回的消息和推送一样处理，未拉完时继续拉取剩余区间
+    async fn handle_gap_pull(
+        conn: &Arc<Box<dyn Connection>>,
+        handler: &ClientMessageHandler<DefClientSystemHandler, DefMessageHandler>,
+        seq_tracker: &Mutex<SeqTracker>,
+        gap_pulls: &Mutex<HashMap<String, PullReq>>,
+        req: PullReq,
+        data: &[u8],
+    ) {
+        let page = match Response::decode(data) {
+            Ok(resp) if resp.code == ResCode::Success as i32 => PullResp::decode(&resp.data[..]).unwrap_or_default(),
+            Ok(resp) => {
+                warn!("Failed to pull missing messages of {}: {}", req.conversation_id, resp.message);
+                return;
+            }
+            Err(e) => {
+                error!("Invalid pull response: {}", e);
+                return;
+            }
+        };
+        let last = page.messages.iter().map(|m| m.conversation_seq).max();
+        for mut msg in page.messages {
+            // 补回的消息不属于当前连接的推送序列
+            msg.seq = 0;
+            Self::dispatch(conn, handler, seq_tracker, gap_pulls, msg).await;
+        }
+        if let (true, Some(last)) = (page.has_more, last) {
+            if last < req.to_seq {
+                let next = PullReq { from_seq: last + 1, ..req };
+                if let Err(e) = Self::pull(conn, gap_pulls, next).await {
+                    error!("Failed to pull missing messages: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn pull(conn: &Arc<Box<dyn Connection>>, gap_pulls: &Mutex<HashMap<String, PullReq>>, req: PullReq) -> Result<()> {
+        let client_id = uuid::Uuid::new_v4().to_string();
+        let data = req.encode_to_vec();
+        gap_pulls.lock().await.insert(client_id.clone(), req);
+        let result = conn.send(ProtoMessage {
+            command: Command::ClientPullMessage as i32,
+            data,
+            client_id: client_id.clone(),
+            ..Default::default()
+        }).await;
+        if result.is_err() {
+            gap_pulls.lock().await.remove(&client_id);
+        }
+        result
+    }
+
     fn spawn_keepalive(&self) {
         let conn = self.conn.clone();
         let is_running = self.is_running.clone();
         let state = self.state.clone();
         let handler = self.handler.clone();
         let config = Arc::clone(&self.config);
+        let heartbeat = self.heartbeat.clone();
+        let mut heartbeat_changed = self.heartbeat_changed.subscribe();
         
         tokio::spawn(async move {
             let config = config.lock().await;
-            let mut interval = interval(config.ping_interval);
+            let ping_interval = config.ping_interval;
             let reconnect_interval = config.reconnect_interval;
             drop(config);
 
             while *is_running.lock().await {
-                interval.tick().await;
+                // 每次按当前前后台状态取间隔，切换前后台时立即发送心跳并重新计时
+                let interval = heartbeat.lock().await.interval(ping_interval);
+                tokio::select! {
+                    _ = sleep(interval) => {}
+                    changed = heartbeat_changed.changed() => {
+                        // 客户端已释放
+                        if changed.is_err() {
+                            break;
+                        }
+                    }
+                }
 
                 // 检查连接状态
                 if !matches!(*state.lock().await, ClientState::Connected | ClientState::Authenticated) {
@@ -494,29 +802,32 @@ where
     async fn flush_messages(
         conn_ref: &mut Option<Arc<Box<dyn Connection>>>,
         conn: &Arc<Mutex<Option<Box<dyn Connection>>>>,
+        outbox: &Outbox,
         msg_buffer: &mut Vec<ProtoMessage>
     ) -> Result<()> {
-        // 确保连接可用
-        if conn_ref.is_none() {
-            *conn_ref = Self::get_connection_ref(conn).await;
-        }
+        // 每次刷新都取最新连接，重连后不会写入旧连接
+        *conn_ref = Self::get_connection_ref(conn).await;
 
         if let Some(ref conn) = conn_ref {
-            let mut failed = false;
+            let mut failed = Vec::new();
             
             // 批量发送所有消息
-            for msg in msg_buffer.drain(..) {
+            let mut msgs = msg_buffer.drain(..);
+            for msg in msgs.by_ref() {
                 debug!("Sending message: {:?}", msg);
-                if let Err(e) = conn.send(msg).await {
+                if let Err(e) = conn.send(msg.clone()).await {
                     error!("Failed to send message: {}", e);
-                    failed = true;
+                    failed.push(msg);
                     break;
                 }
             }
+            failed.extend(msgs);
 
-            if failed {
+            if !failed.is_empty() {
                 *conn_ref = None; // 发送失败时清除连接引用
-                msg_buffer.clear(); // 清空缓冲区
+                // 登录、心跳等控制消息无需重放，其余放回发件箱等待重新认证
+                failed.retain(|m| !matches!(Command::try_from(m.command), Ok(Command::Login | Command::Ping | Command::Pong)));
+                outbox.requeue(failed).await?;
             }
         }
         
@@ -555,4 +866,174 @@ where
             *self.is_running.lock().await = false;
         });
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock;
+
+    /// 接收下一条非心跳消息
+    async fn next(peer: &mut mock::MockPeer) -> ProtoMessage {
+        loop {
+            let msg = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+            if msg.command != Command::Ping as i32 {
+                return msg;
+            }
+        }
+    }
+
+    /// 回复登录请求，`user_id` 为空时表示登录失败
+    async fn reply_login(peer: &mut mock::MockPeer, user_id: &str) {
+        let login = next(peer).await;
+        assert_eq!(login.command, Command::Login as i32);
+        peer.tx.send(ProtoMessage {
+            command: Command::ServerResponse as i32,
+            client_id: login.client_id,
+            data: LoginResp { user_id: user_id.to_string(), ..Default::default() }.encode_to_vec(),
+            ..Default::default()
+        }).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_outbox_replay_after_auth() {
+        let (conn, mut peer) = mock::pair();
+        let client = Client::new(
+            move || {
+                let conn = conn.clone();
+                Box::pin(async move { Ok(Box::new(conn) as Box<dyn Connection>) })
+            },
+            ClientConfig::default(),
+        );
+
+        let msg = |client_id: &str| ProtoMessage {
+            command: Command::ClientSendMessage as i32,
+            client_id: client_id.to_string(),
+            ..Default::default()
+        };
+        // 未连接时进入发件箱，重复的 client_id 被忽略
+        client.send(msg("a")).await.unwrap();
+        client.send(msg("b")).await.unwrap();
+        client.send(msg("a")).await.unwrap();
+        assert_eq!(client.outbox_len().await, 2);
+
+        // 登录失败时消息留在发件箱
+        let (result, _) = tokio::join!(client.connect(), reply_login(&mut peer, ""));
+        assert!(result.is_err());
+        assert_eq!(client.outbox_len().await, 2);
+
+        let (result, _) = tokio::join!(client.connect(), reply_login(&mut peer, "u1"));
+        result.unwrap();
+        for id in ["a", "b"] {
+            assert_eq!(next(&mut peer).await.client_id, id);
+        }
+        assert_eq!(client.outbox_len().await, 0);
+
+        client.send(msg("c")).await.unwrap();
+        assert_eq!(next(&mut peer).await.client_id, "c");
+    }
+
+    #[tokio::test]
+    async fn test_background_keepalive() {
+        let (conn, mut peer) = mock::pair();
+        let client = Client::new(
+            move || {
+                let conn = conn.clone();
+                Box::pin(async move { Ok(Box::new(conn) as Box<dyn Connection>) })
+            },
+            ClientConfig::default(),
+        );
+        let (result, _) = tokio::join!(client.connect(), reply_login(&mut peer, "u1"));
+        result.unwrap();
+
+        let reply = |msg: ProtoMessage| ProtoMessage {
+            command: Command::ServerResponse as i32,
+            client_id: msg.client_id,
+            data: Response { code: ResCode::Success as i32, ..Default::default() }.encode_to_vec(),
+            ..Default::default()
+        };
+        let (result, _) = tokio::join!(client.set_background(true), async {
+            let msg = next(&mut peer).await;
+            assert_eq!(msg.command, Command::SetBackground as i32);
+            peer.tx.send(reply(msg)).unwrap();
+        });
+        result.unwrap();
+        // 切换后立即发送心跳，不等待原来的间隔
+        let ping = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(ping.command, Command::Ping as i32);
+
+        // 重新认证后向新连接同步后台状态
+        let (result, _) = tokio::join!(client.connect(), async {
+            reply_login(&mut peer, "u1").await;
+            let msg = next(&mut peer).await;
+            assert_eq!((msg.command, msg.data.clone()), (Command::SetBackground as i32, vec![1]));
+            peer.tx.send(reply(msg)).unwrap();
+        });
+        result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pull_on_conversation_gap() {
+        let (conn, mut peer) = mock::pair();
+        let client = Client::new(
+            move || {
+                let conn = conn.clone();
+                Box::pin(async move { Ok(Box::new(conn) as Box<dyn Connection>) })
+            },
+            ClientConfig::default(),
+        );
+        let (result, _) = tokio::join!(client.connect(), reply_login(&mut peer, "u1"));
+        result.unwrap();
+
+        let push = |seq: u64, conversation_seq: u64| ProtoMessage {
+            command: Command::ServerPushMsg as i32,
+            seq,
+            conversation_id: "c1".into(),
+            conversation_seq,
+            ..Default::default()
+        };
+        peer.tx.send(push(1, 1)).unwrap();
+        peer.tx.send(push(2, 4)).unwrap();
+
+        let pull = next(&mut peer).await;
+        assert_eq!(pull.command, Command::ClientPullMessage as i32);
+        let req = PullReq::decode(&pull.data[..]).unwrap();
+        assert_eq!((req.conversation_id.as_str(), req.from_seq, req.to_seq), ("c1", 2, 3));
+        assert_eq!(client.last_seq().await, 2);
+        assert_eq!(client.conversation_seq("c1").await, 4);
+
+        // 补回的消息和推送一样回复 ACK，未拉完时继续拉取剩余区间
+        let page = |conversation_seq: u64, has_more: bool| Response {
+            code: ResCode::Success as i32,
+            message: String::new(),
+            data: PullResp {
+                messages: vec![ProtoMessage {
+                    server_msg_id: format!("m{}", conversation_seq),
+                    ..push(0, conversation_seq)
+                }],
+                has_more,
+                ..Default::default()
+            }.encode_to_vec(),
+        };
+        peer.tx.send(ProtoMessage {
+            command: Command::ServerResponse as i32,
+            client_id: pull.client_id,
+            data: page(2, true).encode_to_vec(),
+            ..Default::default()
+        }).unwrap();
+        let ack = next(&mut peer).await;
+        assert_eq!((ack.command, ack.data), (Command::ClientAck as i32, b"m2".to_vec()));
+        let pull = next(&mut peer).await;
+        let req = PullReq::decode(&pull.data[..]).unwrap();
+        assert_eq!((req.from_seq, req.to_seq), (3, 3));
+
+        peer.tx.send(ProtoMessage {
+            command: Command::ServerResponse as i32,
+            client_id: pull.client_id,
+            data: page(3, false).encode_to_vec(),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(next(&mut peer).await.data, b"m3".to_vec());
+        assert_eq!(client.last_seq().await, 2);
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::time::Duration;
 use flare_core::flare_net::net::Platform;
 
@@ -18,6 +19,8 @@ pub struct ClientConfig {
     pub client_id: String,
     pub user_id: String,
     pub language: Option<String>,
+    /// 发件箱文件路径，为空时发件箱仅保存在内存中
+    pub outbox_path: Option<PathBuf>,
 }
 
 impl Default for ClientConfig {
@@ -32,6 +35,7 @@ impl Default for ClientConfig {
             client_id: uuid::Uuid::new_v4().to_string(),
             user_id: String::new(),
             language: None,
+            outbox_path: None,
         }
     }
 }
@@ -102,6 +106,12 @@ impl ClientConfigBuilder {
         self
     }
 
+    /// 设置发件箱文件路径
+    pub fn outbox_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.outbox_path = Some(path.into());
+        self
+    }
+
     /// 构建配置
     pub fn build(self) -> ClientConfig {
         self.config
@@ -2,4 +2,6 @@ pub mod client;
 pub mod handlers;
 pub mod message_handler;
 pub mod sys_handler;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod outbox;
+pub mod sequence;
@@ -0,0 +1,163 @@
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::Message as ProtoMessage;
+use log::warn;
+use prost::Message as ProstMessage;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// 客户端发件箱
+///
+/// 未认证（断线、重连中）时发送的消息先进入发件箱，重新认证后按顺序重放。
+/// 以 `client_id` 去重，可选地持久化到文件，进程重启后仍可恢复。
+#[derive(Clone, Default)]
+pub struct Outbox {
+    inner: Arc<Mutex<OutboxInner>>,
+    path: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct OutboxInner {
+    queue: VecDeque<ProtoMessage>,
+    ids: HashSet<String>,
+}
+
+impl OutboxInner {
+    fn insert(&mut self, mut msg: ProtoMessage, front: bool) -> bool {
+        if msg.client_id.is_empty() {
+            msg.client_id = uuid::Uuid::new_v4().to_string();
+        }
+        if !self.ids.insert(msg.client_id.clone()) {
+            return false;
+        }
+        if front {
+            self.queue.push_front(msg);
+        } else {
+            self.queue.push_back(msg);
+        }
+        true
+    }
+}
+
+impl Outbox {
+    /// 纯内存发件箱
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 基于文件的发件箱，文件已存在时加载其中的消息
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut inner = OutboxInner::default();
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let mut buf = &bytes[..];
+                while !buf.is_empty() {
+                    let msg = ProtoMessage::decode_length_delimited(&mut buf)?;
+                    inner.insert(msg, false);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(FlareErr::Error(format!("failed to read outbox {}: {}", path.display(), e))),
+        }
+        Ok(Self {
+            inner: Arc::new(Mutex::new(inner)),
+            path: Some(path),
+        })
+    }
+
+    /// 追加消息，`client_id` 已在队列中时忽略并返回 false
+    pub async fn push(&self, msg: ProtoMessage) -> Result<bool> {
+        let mut inner = self.inner.lock().await;
+        let added = inner.insert(msg, false);
+        if added {
+            self.persist(&inner).await?;
+        }
+        Ok(added)
+    }
+
+    /// 发送失败的消息放回队首，保持原有顺序
+    pub async fn requeue(&self, msgs: Vec<ProtoMessage>) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        for msg in msgs.into_iter().rev() {
+            inner.insert(msg, true);
+        }
+        self.persist(&inner).await
+    }
+
+    /// 取出全部消息
+    pub async fn drain(&self) -> Result<Vec<ProtoMessage>> {
+        let mut inner = self.inner.lock().await;
+        inner.ids.clear();
+        let msgs: Vec<_> = inner.queue.drain(..).collect();
+        self.persist(&inner).await?;
+        Ok(msgs)
+    }
+
+    pub async fn len(&self) -> usize {
+        self.inner.lock().await.queue.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.inner.lock().await.queue.is_empty()
+    }
+
+    async fn persist(&self, inner: &OutboxInner) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut buf = Vec::new();
+        for msg in &inner.queue {
+            msg.encode_length_delimited(&mut buf)?;
+        }
+        tokio::fs::write(path, buf).await.map_err(|e| {
+            warn!("Failed to persist outbox {}: {}", path.display(), e);
+            FlareErr::Error(format!("failed to write outbox {}: {}", path.display(), e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flare_core::flare_net::net::Command;
+
+    fn msg(client_id: &str) -> ProtoMessage {
+        ProtoMessage {
+            command: Command::ClientSendMessage as i32,
+            client_id: client_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbox_order_and_dedup() {
+        let outbox = Outbox::new();
+        assert!(outbox.push(msg("a")).await.unwrap());
+        assert!(outbox.push(msg("b")).await.unwrap());
+        assert!(!outbox.push(msg("a")).await.unwrap());
+        assert!(outbox.push(msg("")).await.unwrap());
+        outbox.requeue(vec![msg("x"), msg("b")]).await.unwrap();
+
+        let ids: Vec<_> = outbox.drain().await.unwrap().into_iter().map(|m| m.client_id).collect();
+        assert_eq!(ids.len(), 4);
+        assert_eq!(&ids[..3], ["x", "a", "b"]);
+        assert!(!ids[3].is_empty());
+        assert!(outbox.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_outbox_file() {
+        let path = std::env::temp_dir().join(format!("flare-outbox-{}", uuid::Uuid::new_v4()));
+        let outbox = Outbox::open(&path).unwrap();
+        outbox.push(msg("a")).await.unwrap();
+        outbox.push(msg("b")).await.unwrap();
+
+        let reopened = Outbox::open(&path).unwrap();
+        let ids: Vec<_> = reopened.drain().await.unwrap().into_iter().map(|m| m.client_id).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert!(Outbox::open(&path).unwrap().is_empty().await);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
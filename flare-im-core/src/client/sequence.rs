@@ -0,0 +1,93 @@
+use flare_core::flare_net::net::{Message as ProtoMessage, PullReq};
+use log::warn;
+use std::collections::HashMap;
+
+/// 序列号跟踪，检测连接和会话中缺失的消息
+///
+/// 连接序列号每次连接从 1 开始，缺失时只记录日志，未确认的可靠推送由服务端重投或转入离线存储；
+/// 会话序列号跨重连保留，缺失时返回需要补拉的区间。
+#[derive(Debug, Default)]
+pub struct SeqTracker {
+    conn_seq: u64,
+    conversations: HashMap<String, u64>,
+}
+
+impl SeqTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 新连接建立时重置连接序列号
+    pub fn reset_connection(&mut self) {
+        self.conn_seq = 0;
+    }
+
+    /// 当前连接最近收到的序列号
+    pub fn conn_seq(&self) -> u64 {
+        self.conn_seq
+    }
+
+    /// 会话最近收到的序列号
+    pub fn conversation_seq(&self, conversation_id: &str) -> u64 {
+        self.conversations.get(conversation_id).copied().unwrap_or(0)
+    }
+
+    /// 记录收到的消息，会话出现缺口时返回补拉请求
+    pub fn observe(&mut self, msg: &ProtoMessage) -> Option<PullReq> {
+        if msg.seq > 0 {
+            if msg.seq > self.conn_seq + 1 {
+                warn!("Connection sequence gap: expected {}, got {}", self.conn_seq + 1, msg.seq);
+            }
+            self.conn_seq = self.conn_seq.max(msg.seq);
+        }
+
+        if msg.conversation_id.is_empty() || msg.conversation_seq == 0 {
+            return None;
+        }
+        let last = self.conversations.entry(msg.conversation_id.clone()).or_insert(0);
+        // 首次见到的会话不做补拉，由业务方按需拉取历史
+        let gap = if *last > 0 && msg.conversation_seq > *last + 1 {
+            Some(PullReq {
+                conversation_id: msg.conversation_id.clone(),
+                from_seq: *last + 1,
+                to_seq: msg.conversation_seq - 1,
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+        *last = (*last).max(msg.conversation_seq);
+        gap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(seq: u64, conversation_seq: u64) -> ProtoMessage {
+        ProtoMessage {
+            seq,
+            conversation_id: "c1".into(),
+            conversation_seq,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_seq_gap() {
+        let mut tracker = SeqTracker::new();
+        assert!(tracker.observe(&msg(1, 10)).is_none());
+        assert!(tracker.observe(&msg(2, 11)).is_none());
+
+        // 重连后连接序列号重新开始，会话序列号保留
+        tracker.reset_connection();
+        let gap = tracker.observe(&msg(1, 15)).unwrap();
+        assert_eq!((gap.from_seq, gap.to_seq), (12, 14));
+        assert_eq!(tracker.conn_seq(), 1);
+
+        // 补拉到的旧消息不会回退序列号
+        assert!(tracker.observe(&msg(2, 12)).is_none());
+        assert_eq!(tracker.conversation_seq("c1"), 15);
+    }
+}
@@ -0,0 +1,72 @@
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::Message;
+use prost::Message as ProstMessage;
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// 单帧最大长度，超过时视为协议错误
+///
+/// TCP 和 QUIC 共用此编解码，QUIC 流上的消息同样受此限制，超过 16 MiB 的消息需由业务方分片
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// 读取消息体时预分配的最大长度，更长的消息随实际到达的数据增长，
+/// 对端声明的长度不会直接决定分配大小
+const READ_CHUNK: usize = 64 * 1024;
+
+/// 写入一帧：4 字节大端长度前缀 + prost 编码的消息
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, msg: &Message) -> Result<()> {
+    let len = msg.encoded_len();
+    if len > MAX_FRAME_LEN {
+        return Err(FlareErr::InvalidParams(format!("Frame too large: {} bytes", len)));
+    }
+    // 长度和内容一次写入，避免并发发送时交错
+    let mut buf = Vec::with_capacity(4 + len);
+    buf.extend_from_slice(&(len as u32).to_be_bytes());
+    msg.encode(&mut buf).map_err(FlareErr::EncodeError)?;
+    writer.write_all(&buf).await.map_err(io_err)?;
+    writer.flush().await.map_err(io_err)
+}
+
+/// 读取一帧并解码
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Message> {
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes).await.map_err(io_err)?;
+    let len = u32::from_be_bytes(len_bytes) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(FlareErr::ConnectionError(format!("Frame too large: {} bytes", len)));
+    }
+
+    let mut data = Vec::with_capacity(len.min(READ_CHUNK));
+    reader.take(len as u64).read_to_end(&mut data).await.map_err(io_err)?;
+    if data.len() < len {
+        return Err(FlareErr::ConnectionClosed);
+    }
+    Message::decode(&data[..]).map_err(FlareErr::DecodeError)
+}
+
+fn io_err(e: std::io::Error) -> FlareErr {
+    match e.kind() {
+        ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset | ErrorKind::BrokenPipe => FlareErr::ConnectionClosed,
+        _ => FlareErr::ConnectionError(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        let msg = Message { data: vec![7u8; READ_CHUNK * 3], ..Default::default() };
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let writer = tokio::spawn(async move {
+            write_frame(&mut client, &msg).await.unwrap();
+            // 声明的长度大于实际发送的数据
+            client.write_all(&(MAX_FRAME_LEN as u32).to_be_bytes()).await.unwrap();
+            client.write_all(b"short").await.unwrap();
+        });
+        assert_eq!(read_frame(&mut server).await.unwrap().data.len(), READ_CHUNK * 3);
+        writer.await.unwrap();
+        assert!(matches!(read_frame(&mut server).await, Err(FlareErr::ConnectionClosed)));
+    }
+}
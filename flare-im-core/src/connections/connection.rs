@@ -13,6 +13,23 @@ pub enum ConnectionState {
     Error,
 }
 
+/// 建立连接时从握手请求中读取的信息
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HandshakeInfo {
+    /// 请求路径
+    pub path: String,
+    /// 查询参数 `token` 或 `Authorization: Bearer` 中的令牌
+    pub token: Option<String>,
+    /// 查询参数 `platform`，平台名（如 `web`）或编号
+    pub platform: Option<Platform>,
+    /// 查询参数 `client_id`
+    pub client_id: Option<String>,
+    pub user_agent: Option<String>,
+    pub origin: Option<String>,
+    /// `X-Forwarded-For` 原始值
+    pub forwarded_for: Option<String>,
+}
+
 /// 链接标准接口
 #[async_trait]
 pub trait Connection: Send + Sync {
@@ -24,6 +41,16 @@ pub trait Connection: Send + Sync {
     fn platform(&self) -> Platform;
     /// 协议名称
     fn protocol(&self) -> &str;
+    /// 握手信息，不支持的协议返回 None
+    fn handshake(&self) -> Option<&HandshakeInfo> {
+        None
+    }
+    /// 等待握手确认，连接在确认前断开时返回 false
+    ///
+    /// 确认前收到的数据可能来自 0-RTT 早期数据，可被重放。不支持早期数据的协议立即返回 true
+    async fn handshake_confirmed(&self) -> bool {
+        true
+    }
     /// 检查连接是否活跃
     /// 
     /// # 参数
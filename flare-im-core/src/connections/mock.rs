@@ -0,0 +1,202 @@
+use crate::connections::connection::Connection;
+use crate::server::auth_handler::AuthHandler;
+use crate::server::server::Server;
+use crate::server::server_handler::ServerHandler;
+use crate::server::server::ConnectionInfo;
+use crate::server::sys_handler::{DefSystemHandler, SystemHandler};
+use async_trait::async_trait;
+use flare_core::context::AppContext;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::{Command, LoginReq, LoginResp, Message, Platform, Response};
+use prost::Message as ProstMessage;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex, Notify};
+
+/// 测试用的内存连接，服务端持有 `MockConnection`，测试代码持有 `MockPeer`
+#[derive(Clone)]
+pub(crate) struct MockConnection {
+    conn_id: String,
+    inbound: Arc<Mutex<mpsc::UnboundedReceiver<Message>>>,
+    outbound: mpsc::UnboundedSender<Message>,
+    closed: Arc<Notify>,
+}
+
+/// 连接的另一端，模拟客户端
+pub(crate) struct MockPeer {
+    pub tx: mpsc::UnboundedSender<Message>,
+    pub rx: mpsc::UnboundedReceiver<Message>,
+}
+
+impl MockPeer {
+    /// 接收服务端发来的下一条消息，超时返回 None
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Option<Message> {
+        tokio::time::timeout(timeout, self.rx.recv()).await.ok().flatten()
+    }
+}
+
+/// 创建一对互相连通的连接
+pub(crate) fn pair() -> (MockConnection, MockPeer) {
+    let (client_tx, server_rx) = mpsc::unbounded_channel();
+    let (server_tx, client_rx) = mpsc::unbounded_channel();
+    (
+        MockConnection {
+            conn_id: uuid::Uuid::new_v4().to_string(),
+            inbound: Arc::new(Mutex::new(server_rx)),
+            outbound: server_tx,
+            closed: Arc::new(Notify::new()),
+        },
+        MockPeer {
+            tx: client_tx,
+            rx: client_rx,
+        },
+    )
+}
+
+/// `DefAuthHandler` 认证出的用户
+pub(crate) const DEFAULT_USER: &str = "sss";
+
+/// `DefAuthHandler` 接受的登录请求
+pub(crate) fn login_req() -> LoginReq {
+    LoginReq {
+        token: "token".into(),
+        ..Default::default()
+    }
+}
+
+/// 使用 `login_req` 登录为 `DEFAULT_USER`
+pub(crate) async fn login_default<S, A, Y>(server: Arc<Server<S, A, Y>>) -> MockPeer
+where
+    S: ServerHandler + Send + Sync + 'static,
+    A: AuthHandler + Send + Sync + 'static,
+    Y: SystemHandler + Send + Sync + 'static,
+{
+    login(server, login_req()).await
+}
+
+/// 轮询直到条件成立，1 秒内未成立返回 false
+pub(crate) async fn eventually<F, Fut>(mut check: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    for _ in 0..100 {
+        if check().await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    false
+}
+
+/// 建立连接并完成登录，返回客户端一端
+pub(crate) async fn login<S, A, Y>(server: Arc<Server<S, A, Y>>, req: LoginReq) -> MockPeer
+where
+    S: ServerHandler + Send + Sync + 'static,
+    A: AuthHandler + Send + Sync + 'static,
+    Y: SystemHandler + Send + Sync + 'static,
+{
+    login_with_resp(server, req).await.0
+}
+
+/// 同 `login`，同时返回登录响应，登录失败时响应为空
+pub(crate) async fn login_with_resp<S, A, Y>(server: Arc<Server<S, A, Y>>, req: LoginReq) -> (MockPeer, LoginResp)
+where
+    S: ServerHandler + Send + Sync + 'static,
+    A: AuthHandler + Send + Sync + 'static,
+    Y: SystemHandler + Send + Sync + 'static,
+{
+    let (conn, mut peer) = pair();
+    let conn_id = conn.id().to_string();
+    peer.tx.send(Message {
+        command: Command::Login as i32,
+        data: req.encode_to_vec(),
+        ..Default::default()
+    }).unwrap();
+    let handle = server.clone();
+    tokio::spawn(async move { handle.add_connection(Box::new(conn)).await });
+
+    let resp = peer.recv_timeout(Duration::from_secs(1)).await.expect("login response");
+    assert_eq!(resp.command, Command::ServerResponse as i32);
+    // 等待连接注册到用户连接表
+    eventually(|| async {
+        let Some(info) = server.get_connection_info(&conn_id).await else {
+            return false;
+        };
+        let conns = server.get_user_connections(&info.get_user_id()).await;
+        conns.iter().any(|c| c.get_conn_id() == conn_id)
+    }).await;
+    (peer, LoginResp::decode(&resp.data[..]).unwrap_or_default())
+}
+
+#[async_trait]
+impl Connection for MockConnection {
+    fn id(&self) -> &str {
+        &self.conn_id
+    }
+
+    fn remote_addr(&self) -> &str {
+        "mock"
+    }
+
+    fn platform(&self) -> Platform {
+        Platform::Unknown
+    }
+
+    fn protocol(&self) -> &str {
+        "mock"
+    }
+
+    async fn is_active(&self, _timeout: Duration) -> bool {
+        !self.outbound.is_closed()
+    }
+
+    fn send(&self, msg: Message) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            self.outbound.send(msg).map_err(|_| FlareErr::ConnectionClosed)
+        })
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Future<Output = Result<Message>> + Send + '_>> {
+        Box::pin(async move {
+            let mut inbound = self.inbound.lock().await;
+            tokio::select! {
+                msg = inbound.recv() => msg.ok_or(FlareErr::ConnectionClosed),
+                _ = self.closed.notified() => Err(FlareErr::ConnectionClosed),
+            }
+        })
+    }
+
+    fn close(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            // 不能等待 inbound 锁，接收任务可能正持有它
+            self.closed.notify_one();
+            Ok(())
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Connection> {
+        Box::new(self.clone())
+    }
+}
+
+/// 拒绝所有新连接的系统处理器，其余命令按默认实现处理
+pub(crate) struct RejectNew;
+
+#[async_trait]
+impl SystemHandler for RejectNew {
+    async fn handle_new_connection(&self, _ctx: &AppContext, _conn: &ConnectionInfo) -> Result<Response> {
+        Err(FlareErr::internal_error("rejected"))
+    }
+    async fn handle_set_background(&self, ctx: &AppContext, background: bool) -> Result<Response> {
+        DefSystemHandler.handle_set_background(ctx, background).await
+    }
+    async fn handle_set_language(&self, ctx: &AppContext, language: String) -> Result<Response> {
+        DefSystemHandler.handle_set_language(ctx, language).await
+    }
+    async fn handle_close(&self, ctx: &AppContext) -> Result<Response> {
+        DefSystemHandler.handle_close(ctx).await
+    }
+}
@@ -1,5 +1,8 @@
 mod connection;
-pub use connection::{Connection, ConnectionState};
+pub use connection::{Connection, ConnectionState, HandshakeInfo};
+
+#[cfg(test)]
+pub(crate) mod mock;
 
 #[cfg(any(feature = "client", feature = "server"))]
 pub mod ws;
@@ -7,12 +10,21 @@ pub mod ws;
 #[cfg(any(feature = "client", feature = "server"))]
 pub mod quic;
 
+#[cfg(any(feature = "client", feature = "server"))]
+pub mod tcp;
+
+#[cfg(any(feature = "client", feature = "server"))]
+pub mod codec;
+
 #[cfg(any(feature = "client", feature = "server"))]
 pub use ws::WsConnection;
 
 #[cfg(any(feature = "client", feature = "server"))]
 pub use quic::QuicConnection;
 
+#[cfg(any(feature = "client", feature = "server"))]
+pub use tcp::TcpConnection;
+
 // 导出 quic 配置
 #[cfg(any(feature = "client", feature = "server"))]
 pub mod quic_conf {
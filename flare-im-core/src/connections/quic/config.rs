@@ -12,6 +12,7 @@ use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, Server
 use rustls::SignatureScheme;
 use rustls::pki_types::UnixTime;
 use rustls::RootCertStore;
+use rustls::server::ServerSessionMemoryCache;
 
 /// 添加初始化函数
 pub fn init_crypto() -> anyhow::Result<()> {
@@ -27,6 +28,7 @@ pub const MAX_CONCURRENT_BIDI_STREAMS: u32 = 100;  // 最大并发双向流数
 pub const MAX_DATA: u32 = 10 * 1024 * 1024;       // 最大数据量 (10MB)
 pub const MAX_STREAM_DATA: u32 = 1 * 1024 * 1024; // 单个流最大数据量 (1MB)
 pub const KEEP_ALIVE_INTERVAL: u64 = 10;           // 保活间隔 (秒)
+pub const SESSION_CACHE_SIZE: usize = 4096;      // 服务端会话缓存数量，用于会话恢复和 0-RTT
 pub const IDLE_TIMEOUT: u64 = 60;                 // 空闲超时时间 (秒)
 pub const MAX_SIZE: usize = 1024 * 1024;          // 单次读写最大大小 (1MB)
 
@@ -49,63 +51,65 @@ pub fn create_transport_config() -> TransportConfig {
     transport
 }
 
+/// 跳过服务端证书校验，仅用于测试环境
+#[derive(Debug)]
+pub(crate) struct SkipServerVerification;
+
+impl ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ED25519,
+        ]
+    }
+}
+
 /// 创建客户端配置
 pub fn create_client_config(cert_path: &str, is_test: bool) -> anyhow::Result<ClientConfig> {
     init_crypto()?;
     
     let client_crypto = if is_test {
-        #[derive(Debug)]
-        struct SkipVerifier;
-        impl ServerCertVerifier for SkipVerifier {
-            fn verify_server_cert(
-                &self,
-                _end_entity: &CertificateDer<'_>,
-                _intermediates: &[CertificateDer<'_>],
-                _server_name: &rustls::pki_types::ServerName<'_>,
-                _ocsp: &[u8],
-                _now: UnixTime,
-            ) -> Result<ServerCertVerified, rustls::Error> {
-                Ok(ServerCertVerified::assertion())
-            }
-
-            fn verify_tls12_signature(
-                &self,
-                _message: &[u8],
-                _cert: &CertificateDer<'_>,
-                _dss: &rustls::DigitallySignedStruct,
-            ) -> Result<HandshakeSignatureValid, rustls::Error> {
-                Ok(HandshakeSignatureValid::assertion())
-            }
-
-            fn verify_tls13_signature(
-                &self,
-                _message: &[u8],
-                _cert: &CertificateDer<'_>,
-                _dss: &rustls::DigitallySignedStruct,
-            ) -> Result<HandshakeSignatureValid, rustls::Error> {
-                Ok(HandshakeSignatureValid::assertion())
-            }
-
-            fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
-                vec![
-                    SignatureScheme::RSA_PSS_SHA256,
-                    SignatureScheme::RSA_PSS_SHA384,
-                    SignatureScheme::RSA_PSS_SHA512,
-                    SignatureScheme::RSA_PKCS1_SHA256,
-                    SignatureScheme::RSA_PKCS1_SHA384,
-                    SignatureScheme::RSA_PKCS1_SHA512,
-                    SignatureScheme::ECDSA_NISTP384_SHA384,
-                    SignatureScheme::ECDSA_NISTP256_SHA256,
-                    SignatureScheme::ED25519,
-                ]
-            }
-        }
-
         let mut config = rustls::ClientConfig::builder()
             .with_root_certificates(RootCertStore::empty())
             .with_no_client_auth();
         config.dangerous()
-            .set_certificate_verifier(Arc::new(SkipVerifier));
+            .set_certificate_verifier(Arc::new(SkipServerVerification));
         config.alpn_protocols = ALPN_QUIC_HTTP.iter().map(|&x| x.into()).collect();
         config
     } else {
@@ -121,18 +125,17 @@ pub fn create_client_config(cert_path: &str, is_test: bool) -> anyhow::Result<Cl
         config
     };
 
+    // 会话票据保存在配置中，复用同一配置重连时可 0-RTT 恢复
+    let mut client_crypto = client_crypto;
+    client_crypto.enable_early_data = true;
     let mut client_config = ClientConfig::new(Arc::new(QuicClientConfig::try_from(client_crypto)?));
     client_config.transport_config(Arc::new(create_transport_config()));
     
     Ok(client_config)
 }
 
-/// 创建服务端配置
-pub fn create_server_config(cert_path: &str, key_path: &str) -> anyhow::Result<ServerConfig> {
-    // 确保加密提供程序已初始化
-    init_crypto()?;
-    
-    // 读取证书和私钥
+/// 读取私钥，`.der` 文件按 PKCS #8 处理，其余按 PEM 解析
+pub fn load_private_key(key_path: &str) -> anyhow::Result<PrivateKeyDer<'static>> {
     let key = fs::read(key_path).context("failed to read private key")?;
     let key = if Path::new(key_path).extension()
         .and_then(|x| x.to_str())
@@ -144,7 +147,11 @@ pub fn create_server_config(cert_path: &str, key_path: &str) -> anyhow::Result<S
             .context("malformed PKCS #1 private key")?
             .ok_or_else(|| anyhow::Error::msg("no private keys found"))?
     };
+    Ok(key)
+}
 
+/// 读取证书链，`.der` 文件为单个证书，其余按 PEM 解析
+pub fn load_cert_chain(cert_path: &str) -> anyhow::Result<Vec<CertificateDer<'static>>> {
     let cert_chain = fs::read(cert_path).context("failed to read certificate chain")?;
     let cert_chain = if Path::new(cert_path).extension()
         .and_then(|x| x.to_str())
@@ -156,18 +163,90 @@ pub fn create_server_config(cert_path: &str, key_path: &str) -> anyhow::Result<S
             .collect::<Result<_, _>>()
             .context("invalid PEM-encoded certificate")?
     };
+    Ok(cert_chain)
+}
+
+/// 创建服务端配置
+pub fn create_server_config(cert_path: &str, key_path: &str) -> anyhow::Result<ServerConfig> {
+    // 确保加密提供程序已初始化
+    init_crypto()?;
+    
+    // 读取证书和私钥
+    let key = load_private_key(key_path)?;
+    let cert_chain = load_cert_chain(cert_path)?;
 
     let mut server_crypto = rustls::ServerConfig::builder()
         .with_no_client_auth()
         .with_single_cert(cert_chain, key)?;
     server_crypto.alpn_protocols = ALPN_QUIC_HTTP.iter().map(|&x| x.into()).collect();
+    // 有状态的会话缓存才允许 0-RTT，票据只能使用一次；QUIC 要求早期数据上限为 u32::MAX
+    server_crypto.session_storage = ServerSessionMemoryCache::new(SESSION_CACHE_SIZE);
+    server_crypto.max_early_data_size = u32::MAX;
     
     // 创建服务端配置
     let mut server_config =
         quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(server_crypto)?));
     // 配置传输设置
     server_config.transport_config(Arc::new(create_transport_config()));
+    // 客户端切换网络后地址变化时保持连接
+    server_config.migration(true);
 
     Ok(server_config)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/certs/cert.pem");
+    const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/certs/key.pem");
+
+    #[tokio::test]
+    async fn test_zero_rtt_resumption() {
+        let server = quinn::Endpoint::server(create_server_config(CERT, KEY).unwrap(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Some(incoming) = server.accept().await {
+                let conn = match incoming.accept().unwrap().into_0rtt() {
+                    Ok((conn, _)) => conn,
+                    Err(connecting) => connecting.await.unwrap(),
+                };
+                tokio::spawn(async move {
+                    let mut recv = conn.accept_uni().await.unwrap();
+                    let data = recv.read_to_end(1024).await.unwrap();
+                    let mut send = conn.open_uni().await.unwrap();
+                    send.write_all(&data).await.unwrap();
+                    send.finish().unwrap();
+                    conn.closed().await;
+                });
+            }
+        });
+
+        let mut client = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+        client.set_default_client_config(create_client_config(CERT, true).unwrap());
+
+        // 首次连接完成完整握手并取得会话票据
+        let connecting = client.connect(addr, "localhost").unwrap();
+        let connecting = match connecting.into_0rtt() {
+            Ok(_) => panic!("0-RTT without session ticket"),
+            Err(connecting) => connecting,
+        };
+        let conn = connecting.await.unwrap();
+        let mut send = conn.open_uni().await.unwrap();
+        send.write_all(b"first").await.unwrap();
+        send.finish().unwrap();
+        let mut recv = conn.accept_uni().await.unwrap();
+        assert_eq!(recv.read_to_end(1024).await.unwrap(), b"first");
+        conn.close(0u32.into(), b"done");
+
+        // 复用同一个 endpoint 重连时发送 0-RTT 数据
+        let (conn, accepted) = client.connect(addr, "localhost").unwrap().into_0rtt().unwrap();
+        let mut send = conn.open_uni().await.unwrap();
+        send.write_all(b"early").await.unwrap();
+        send.finish().unwrap();
+        assert!(accepted.await);
+        let mut recv = conn.accept_uni().await.unwrap();
+        assert_eq!(recv.read_to_end(1024).await.unwrap(), b"early");
+    }
+}
@@ -1,19 +1,64 @@
+use crate::connections::codec::{read_frame, write_frame};
 use crate::connections::connection::{Connection, ConnectionState};
 use flare_core::error::{FlareErr, Result};
 use log::debug;
-use prost::Message as ProstMessage;
 use flare_core::flare_net::net::{Command, Message, Platform};
-use quinn::{Connection as QuinnConnection, RecvStream, SendStream};
+use prost::Message as ProstMessage;
+use quinn::{Connection as QuinnConnection, RecvStream, SendStream, ZeroRttAccepted};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, watch, Mutex};
 use std::pin::Pin;
 use std::future::Future;
 use async_trait::async_trait;
 
+/// 接收队列长度，读取任务在队列满时等待
+const INBOUND_CAPACITY: usize = 256;
+/// 请求流等待响应的最长时间，超时后不再写回响应
+const REPLY_STREAM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 等待写回响应的请求流，client_id -> (流, 登记时间)
+type ReplyStreams = Arc<Mutex<HashMap<String, (SendStream, Instant)>>>;
+
+/// QUIC 流的使用方式
+///
+/// 默认所有消息走第一条双向流（控制流）。超过 `large_message_threshold` 的消息使用独立的单向流，
+/// 开启 `request_streams` 后 `ClientRequest` 使用独立的双向流，响应写回同一条流。
+/// 不同流之间不保证顺序。
+#[derive(Debug, Clone)]
+pub struct QuicStreamOptions {
+    pub request_streams: bool,
+    pub large_message_threshold: usize,
+}
+
+impl Default for QuicStreamOptions {
+    fn default() -> Self {
+        Self {
+            request_streams: false,
+            large_message_threshold: 64 * 1024,
+        }
+    }
+}
+
+impl QuicStreamOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request_streams(mut self, enabled: bool) -> Self {
+        self.request_streams = enabled;
+        self
+    }
+
+    pub fn large_message_threshold(mut self, threshold: usize) -> Self {
+        self.large_message_threshold = threshold;
+        self
+    }
+}
+
 #[derive(Clone)]
 pub struct QuicConnection {
-    // 基础信息
     // 基础信息
     conn_id: String,
     protocol: String,
@@ -25,8 +70,16 @@ pub struct QuicConnection {
 
     // QUIC 连接
     conn: Arc<QuinnConnection>,
+    // 控制流
     send_stream: Arc<Mutex<SendStream>>,
-    recv_stream: Arc<Mutex<RecvStream>>,
+    // 所有流上收到的消息
+    inbound_tx: mpsc::Sender<Result<Message>>,
+    inbound: Arc<Mutex<mpsc::Receiver<Result<Message>>>>,
+    // 对端通过独立双向流发来的请求，响应写回同一条流
+    reply_streams: ReplyStreams,
+    options: QuicStreamOptions,
+    // 握手是否已确认，接受 0-RTT 时在确认前为 false
+    handshake_confirmed: watch::Receiver<bool>,
 }
 
 impl QuicConnection {
@@ -41,16 +94,7 @@ impl QuicConnection {
             .await
             .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
 
-        Ok(Self {
-            conn_id: uuid::Uuid::new_v4().to_string(),
-            protocol: "quic".to_string(),
-            remote_addr,
-            state: Arc::new(Mutex::new(ConnectionState::Connected)),
-            last_active: Arc::new(Mutex::new(Instant::now())),
-            conn: Arc::new(conn),
-            send_stream: Arc::new(Mutex::new(send)),
-            recv_stream: Arc::new(Mutex::new(recv)),
-        })
+        Self::with_streams(conn, send, recv, remote_addr).await
     }
 
     // 客户端使用的构造函数
@@ -64,16 +108,7 @@ impl QuicConnection {
             .await
             .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
 
-        Ok(Self {
-            conn_id: uuid::Uuid::new_v4().to_string(),
-            protocol: "quic".to_string(),
-            remote_addr,
-            state: Arc::new(Mutex::new(ConnectionState::Connected)),
-            last_active: Arc::new(Mutex::new(Instant::now())),
-            conn: Arc::new(conn),
-            send_stream: Arc::new(Mutex::new(send)),
-            recv_stream: Arc::new(Mutex::new(recv)),
-        })
+        Self::with_streams(conn, send, recv, remote_addr).await
     }
 
     pub async fn with_streams(
@@ -82,21 +117,140 @@ impl QuicConnection {
         recv: RecvStream,
         remote_addr: String,
     ) -> Result<Self> {
+        let (inbound_tx, inbound) = mpsc::channel(INBOUND_CAPACITY);
+        let conn = Arc::new(conn);
+        let reply_streams = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(read_control_stream(recv, inbound_tx.clone()));
+        tokio::spawn(accept_streams(conn.clone(), inbound_tx.clone(), reply_streams.clone()));
+
         Ok(Self {
             conn_id: uuid::Uuid::new_v4().to_string(),
             protocol: "quic".to_string(),
             remote_addr,
             state: Arc::new(Mutex::new(ConnectionState::Connected)),
             last_active: Arc::new(Mutex::new(Instant::now())),
-            conn: Arc::new(conn),
+            conn,
             send_stream: Arc::new(Mutex::new(send)),
-            recv_stream: Arc::new(Mutex::new(recv)),
+            inbound_tx,
+            inbound: Arc::new(Mutex::new(inbound)),
+            reply_streams,
+            options: QuicStreamOptions::default(),
+            handshake_confirmed: watch::channel(true).1,
         })
     }
 
+    /// 设置流的使用方式
+    pub fn with_options(mut self, options: QuicStreamOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 服务端接受 0-RTT 时传入握手确认的 future，确认前收到的数据可能被重放
+    pub fn with_zero_rtt(mut self, accepted: ZeroRttAccepted) -> Self {
+        let (tx, rx) = watch::channel(false);
+        tokio::spawn(async move {
+            // 连接在握手确认前断开时返回 false
+            if accepted.await {
+                let _ = tx.send(true);
+            }
+        });
+        self.handshake_confirmed = rx;
+        self
+    }
+
     async fn update_last_active(&self) {
         *self.last_active.lock().await = Instant::now();
     }
+
+    /// 在独立的双向流上发送请求，响应读回接收队列
+    async fn send_request_stream(&self, msg: &Message) -> Result<()> {
+        let (mut send, mut recv) = self.conn.open_bi().await
+            .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
+        write_frame(&mut send, msg).await?;
+        let _ = send.finish();
+        let tx = self.inbound_tx.clone();
+        tokio::spawn(async move {
+            match read_frame(&mut recv).await {
+                Ok(resp) => {
+                    let _ = tx.send(Ok(resp)).await;
+                }
+                Err(e) => debug!("Request stream closed without response: {}", e),
+            }
+        });
+        Ok(())
+    }
+
+    /// 在独立的单向流上发送一条消息
+    async fn send_uni_stream(&self, msg: &Message) -> Result<()> {
+        let mut send = self.conn.open_uni().await
+            .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
+        write_frame(&mut send, msg).await?;
+        let _ = send.finish();
+        Ok(())
+    }
+}
+
+/// 读取控制流，出错后把错误交给 `receive`
+async fn read_control_stream(mut recv: RecvStream, tx: mpsc::Sender<Result<Message>>) {
+    loop {
+        tokio::select! {
+            _ = tx.closed() => return,
+            msg = read_frame(&mut recv) => {
+                let failed = msg.is_err();
+                if tx.send(msg).await.is_err() || failed {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// 接收对端新打开的流，每条流上只有一条消息
+async fn accept_streams(
+    conn: Arc<QuinnConnection>,
+    tx: mpsc::Sender<Result<Message>>,
+    reply_streams: ReplyStreams,
+) {
+    loop {
+        tokio::select! {
+            _ = tx.closed() => break,
+            uni = conn.accept_uni() => {
+                let Ok(mut recv) = uni else { break };
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match read_frame(&mut recv).await {
+                        Ok(msg) => {
+                            let _ = tx.send(Ok(msg)).await;
+                        }
+                        Err(e) => debug!("Failed to read uni stream: {}", e),
+                    }
+                });
+            }
+            bi = conn.accept_bi() => {
+                let Ok((mut send, mut recv)) = bi else { break };
+                let tx = tx.clone();
+                let reply_streams = reply_streams.clone();
+                tokio::spawn(async move {
+                    match read_frame(&mut recv).await {
+                        Ok(msg) => {
+                            // 只有请求会收到响应，先登记响应流再交给处理器，保证响应能找到对应的流
+                            if msg.command == Command::ClientRequest as i32 && !msg.client_id.is_empty() {
+                                let mut streams = reply_streams.lock().await;
+                                streams.retain(|_, (_, at)| at.elapsed() < REPLY_STREAM_TIMEOUT);
+                                streams.insert(msg.client_id.clone(), (send, Instant::now()));
+                            } else {
+                                let _ = send.finish();
+                            }
+                            let _ = tx.send(Ok(msg)).await;
+                        }
+                        Err(e) => debug!("Failed to read request stream: {}", e),
+                    }
+                });
+            }
+        }
+    }
+    // 连接已关闭，未响应的请求流不再需要
+    reply_streams.lock().await.clear();
 }
 
 #[async_trait]
@@ -117,6 +271,10 @@ impl Connection for QuicConnection {
         &self.protocol
     }
 
+    async fn handshake_confirmed(&self) -> bool {
+        self.handshake_confirmed.clone().wait_for(|confirmed| *confirmed).await.is_ok()
+    }
+
     async fn is_active(&self, timeout: Duration) -> bool {
         // 检查连接状态
         let state = *self.state.lock().await;
@@ -134,30 +292,31 @@ impl Connection for QuicConnection {
     }
 
     fn send(&self, msg: Message) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
-        let send_stream = self.send_stream.clone();
         Box::pin(async move {
             debug!("Sending message: command={:?}, data_len={}", 
                 Command::try_from(msg.command).unwrap_or(Command::CmdUnknown), 
                 msg.data.len()
             );
             
-            // 编码消息
-            let mut data = Vec::new();
-            msg.encode(&mut data)
-                .map_err(|e| FlareErr::EncodeError(e))?;
-            
-            // 发送长度前缀
-            let len = (data.len() as u32).to_be_bytes();
-            send_stream.lock().await
-                .write_all(&len)
-                .await
-                .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
-            
-            // 发送数据
-            send_stream.lock().await
-                .write_all(&data)
-                .await
-                .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
+            let command = Command::try_from(msg.command).unwrap_or(Command::CmdUnknown);
+            let reply_stream = match command {
+                Command::ServerResponse if !msg.client_id.is_empty() => {
+                    self.reply_streams.lock().await.remove(&msg.client_id).map(|(send, _)| send)
+                }
+                _ => None,
+            };
+            if let Some(mut send) = reply_stream {
+                // 响应写回请求所在的流
+                write_frame(&mut send, &msg).await?;
+                let _ = send.finish();
+            } else if self.options.request_streams && command == Command::ClientRequest && !msg.client_id.is_empty() {
+                self.send_request_stream(&msg).await?;
+            } else if msg.encoded_len() > self.options.large_message_threshold {
+                self.send_uni_stream(&msg).await?;
+            } else {
+                // 长度前缀帧，与 TCP 共用编码
+                write_frame(&mut *self.send_stream.lock().await, &msg).await?;
+            }
             
             self.update_last_active().await;
             Ok(())
@@ -165,28 +324,18 @@ impl Connection for QuicConnection {
     }
 
     fn receive(&self) -> Pin<Box<dyn Future<Output = Result<Message>> + Send + '_>> {
-        let recv_stream = self.recv_stream.clone();
-        debug!("Receiving message");
         Box::pin(async move {
-            // 读取长度前缀
-            let mut len_bytes = [0u8; 4];
-            recv_stream.lock().await
-                .read_exact(&mut len_bytes)
-                .await
-                .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
-            
-            let len = u32::from_be_bytes(len_bytes) as usize;
-            
-            // 读取消息数据
-            let mut data = vec![0u8; len];
-            recv_stream.lock().await
-                .read_exact(&mut data)
-                .await
-                .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
-            
-            // 解码消息
-            let msg = Message::decode(&data[..])
-                .map_err(|e| FlareErr::DecodeError(e))?;
+            let msg = match self.inbound.lock().await.recv().await {
+                Some(Ok(msg)) => msg,
+                Some(Err(e)) => {
+                    *self.state.lock().await = ConnectionState::Disconnected;
+                    return Err(e);
+                }
+                None => {
+                    *self.state.lock().await = ConnectionState::Disconnected;
+                    return Err(FlareErr::ConnectionClosed);
+                }
+            };
             
             debug!("Received message: command={:?}, data_len={}", 
                 Command::try_from(msg.command).unwrap_or(Command::CmdUnknown), 
@@ -202,8 +351,10 @@ impl Connection for QuicConnection {
         let state = self.state.clone();
         let send_stream = self.send_stream.clone();
         let conn = self.conn.clone();
+        let reply_streams = self.reply_streams.clone();
         Box::pin(async move {
             *state.lock().await = ConnectionState::Disconnected;
+            reply_streams.lock().await.clear();
             
             if let Ok(mut send) = send_stream.try_lock() {
                 let _ = send.finish();
@@ -217,4 +368,71 @@ impl Connection for QuicConnection {
     fn clone_box(&self) -> Box<dyn Connection> {
         Box::new(self.clone())
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::quic_conf::{create_client_config, create_server_config};
+
+    const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/certs/cert.pem");
+    const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/certs/key.pem");
+
+    #[tokio::test]
+    async fn test_multiplexed_streams() {
+        let server = quinn::Endpoint::server(create_server_config(CERT, KEY).unwrap(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = server.local_addr().unwrap();
+        let accept = tokio::spawn(async move {
+            let conn = server.accept().await.unwrap().await.unwrap();
+            (server, QuicConnection::new(conn, addr.to_string()).await.unwrap())
+        });
+
+        let mut endpoint = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+        endpoint.set_default_client_config(create_client_config(CERT, true).unwrap());
+        let conn = endpoint.connect(addr, "localhost").unwrap().await.unwrap();
+        let client = QuicConnection::connect(conn, addr.to_string()).await.unwrap()
+            .with_options(QuicStreamOptions::new().request_streams(true).large_message_threshold(1024));
+
+        // 第一条消息建立控制流
+        client.send(Message { command: Command::Ping as i32, ..Default::default() }).await.unwrap();
+        let (_endpoint, server_conn) = accept.await.unwrap();
+        assert_eq!(server_conn.receive().await.unwrap().command, Command::Ping as i32);
+
+        // 请求走独立双向流，响应写回同一条流
+        client.send(Message {
+            command: Command::ClientRequest as i32,
+            client_id: "r1".into(),
+            ..Default::default()
+        }).await.unwrap();
+        let req = server_conn.receive().await.unwrap();
+        assert_eq!(req.client_id, "r1");
+        assert!(server_conn.reply_streams.lock().await.contains_key("r1"));
+        server_conn.send(Message {
+            command: Command::ServerResponse as i32,
+            client_id: "r1".into(),
+            ..Default::default()
+        }).await.unwrap();
+        assert!(server_conn.reply_streams.lock().await.is_empty());
+        assert_eq!(client.receive().await.unwrap().client_id, "r1");
+
+        // 大消息走单向流
+        server_conn.send(Message {
+            command: Command::ServerPushMsg as i32,
+            data: vec![7; 64 * 1024],
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(client.receive().await.unwrap().data.len(), 64 * 1024);
+
+        // 未响应的请求流在连接关闭后释放
+        client.send(Message {
+            command: Command::ClientRequest as i32,
+            client_id: "r2".into(),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(server_conn.receive().await.unwrap().client_id, "r2");
+        assert!(server_conn.reply_streams.lock().await.contains_key("r2"));
+
+        client.close().await.unwrap();
+        assert!(server_conn.receive().await.is_err());
+        assert!(crate::connections::mock::eventually(|| async { server_conn.reply_streams.lock().await.is_empty() }).await);
+    }
+}
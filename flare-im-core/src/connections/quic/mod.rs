@@ -1,4 +1,4 @@
 mod conn;
 
-pub use conn::QuicConnection;
+pub use conn::{QuicConnection, QuicStreamOptions};
 pub mod config;
\ No newline at end of file
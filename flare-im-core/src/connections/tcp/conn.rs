@@ -0,0 +1,172 @@
+use crate::connections::codec::{read_frame, write_frame};
+use crate::connections::connection::{Connection, ConnectionState};
+use async_trait::async_trait;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::{Command, Message, Platform};
+use log::debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::Mutex;
+
+/// 原始 TCP 连接，帧格式与 QUIC 相同：4 字节大端长度前缀 + prost 消息
+#[derive(Clone)]
+pub struct TcpConnection {
+    // 基础信息
+    conn_id: String,
+    protocol: String,
+    remote_addr: String,
+    // 连接状态
+    state: Arc<Mutex<ConnectionState>>,
+    // 最后活动时间
+    last_active: Arc<Mutex<Instant>>,
+    // TCP 流
+    writer: Arc<Mutex<OwnedWriteHalf>>,
+    reader: Arc<Mutex<OwnedReadHalf>>,
+}
+
+impl TcpConnection {
+    pub fn new(stream: TcpStream, remote_addr: String) -> Self {
+        if let Err(e) = stream.set_nodelay(true) {
+            debug!("Failed to set TCP_NODELAY: {}", e);
+        }
+        let (reader, writer) = stream.into_split();
+        Self {
+            conn_id: uuid::Uuid::new_v4().to_string(),
+            protocol: "tcp".to_string(),
+            remote_addr,
+            state: Arc::new(Mutex::new(ConnectionState::Connected)),
+            last_active: Arc::new(Mutex::new(Instant::now())),
+            writer: Arc::new(Mutex::new(writer)),
+            reader: Arc::new(Mutex::new(reader)),
+        }
+    }
+
+    /// 客户端连接服务端
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await
+            .map_err(|e| FlareErr::ConnectionError(format!("TCP connection failed: {}", e)))?;
+        let remote_addr = stream.peer_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        Ok(Self::new(stream, remote_addr))
+    }
+
+    async fn update_last_active(&self) {
+        *self.last_active.lock().await = Instant::now();
+    }
+}
+
+#[async_trait]
+impl Connection for TcpConnection {
+    fn id(&self) -> &str {
+        &self.conn_id
+    }
+
+    fn remote_addr(&self) -> &str {
+        &self.remote_addr
+    }
+
+    fn platform(&self) -> Platform {
+        Platform::Unknown
+    }
+
+    fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    async fn is_active(&self, timeout: Duration) -> bool {
+        // 检查连接状态
+        if *self.state.lock().await != ConnectionState::Connected {
+            return false;
+        }
+        // 检查最后活动时间
+        self.last_active.lock().await.elapsed() <= timeout
+    }
+
+    fn send(&self, msg: Message) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            write_frame(&mut *self.writer.lock().await, &msg).await?;
+            self.update_last_active().await;
+            Ok(())
+        })
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Future<Output = Result<Message>> + Send + '_>> {
+        Box::pin(async move {
+            let msg = match read_frame(&mut *self.reader.lock().await).await {
+                Ok(msg) => msg,
+                Err(e) => {
+                    *self.state.lock().await = ConnectionState::Disconnected;
+                    return Err(e);
+                }
+            };
+            debug!("Received message: command={:?}, data_len={}",
+                Command::try_from(msg.command).unwrap_or(Command::CmdUnknown), msg.data.len());
+            self.update_last_active().await;
+            Ok(msg)
+        })
+    }
+
+    fn close(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            *self.state.lock().await = ConnectionState::Disconnected;
+            if let Err(e) = self.writer.lock().await.shutdown().await {
+                debug!("Failed to shutdown TCP stream: {}", e);
+            }
+            Ok(())
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Connection> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::router::Router;
+    use crate::server::server::Server;
+    use flare_core::context::AppContext;
+    use flare_core::flare_net::net::{LoginReq, LoginResp, ResCode, Response};
+    use prost::Message as ProstMessage;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_tcp_round_trip() {
+        let router = Router::new().route("echo", |_ctx: AppContext, req: LoginReq| async move {
+            Ok(LoginResp { user_id: req.user_id, ..Default::default() })
+        });
+        let server = Arc::new(Server::default().with_routes(router));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, remote) = listener.accept().await.unwrap();
+            server.add_connection(Box::new(TcpConnection::new(stream, remote.to_string()))).await;
+        });
+
+        let conn = TcpConnection::connect(addr).await.unwrap();
+        conn.send(Message {
+            command: Command::Login as i32,
+            data: crate::connections::mock::login_req().encode_to_vec(),
+            ..Default::default()
+        }).await.unwrap();
+        let reply = conn.receive().await.unwrap();
+        assert_eq!(LoginResp::decode(&reply.data[..]).unwrap().user_id, "sss");
+
+        conn.send(Message {
+            command: Command::ClientRequest as i32,
+            route: "echo".into(),
+            data: LoginReq { user_id: "tcp".into(), ..Default::default() }.encode_to_vec(),
+            ..Default::default()
+        }).await.unwrap();
+        let resp = Response::decode(&conn.receive().await.unwrap().data[..]).unwrap();
+        assert_eq!(resp.code, ResCode::Success as i32);
+        assert_eq!(LoginResp::decode(&resp.data[..]).unwrap().user_id, "tcp");
+    }
+}
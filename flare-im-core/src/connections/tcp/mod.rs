@@ -0,0 +1,3 @@
+pub mod conn;
+
+pub use conn::TcpConnection;
@@ -1,5 +1,5 @@
 use std::future::Future;
-use crate::connections::connection::{Connection, ConnectionState};
+use crate::connections::connection::{Connection, ConnectionState, HandshakeInfo};
 use flare_core::error::{FlareErr, Result};
 use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
@@ -20,6 +20,7 @@ pub struct WsConnection<S> {
     conn_id: String,
     protocol: String,
     remote_addr: String,
+    handshake: Option<HandshakeInfo>,
     // 连接状态
     state: Arc<Mutex<ConnectionState>>,
     // 最后活动时间
@@ -40,6 +41,7 @@ where
             conn_id: uuid::Uuid::new_v4().to_string(),
             protocol: "websocket".to_string(),
             remote_addr,
+            handshake: None,
             state: Arc::new(Mutex::new(ConnectionState::Connected)),
             last_active: Arc::new(Mutex::new(Instant::now())),
             writer: Arc::new(Mutex::new(writer)),
@@ -47,6 +49,12 @@ where
         }
     }
 
+    /// 设置握手信息
+    pub fn with_handshake(mut self, handshake: HandshakeInfo) -> Self {
+        self.handshake = Some(handshake);
+        self
+    }
+
     /// 更新最后活动时间
     async fn update_last_active(&self) {
         *self.last_active.lock().await = Instant::now();
@@ -67,6 +75,10 @@ where
         &self.remote_addr
     }
 
+    fn handshake(&self) -> Option<&HandshakeInfo> {
+        self.handshake.as_ref()
+    }
+
     fn platform(&self) -> Platform {
         Platform::Unknown
     }
@@ -188,6 +200,7 @@ where
             conn_id: self.conn_id.clone(),
             protocol: self.protocol.clone(),
             remote_addr: self.remote_addr.clone(),
+            handshake: self.handshake.clone(),
             state: self.state.clone(),
             last_active: self.last_active.clone(),
             writer: self.writer.clone(),
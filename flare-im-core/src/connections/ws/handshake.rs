@@ -0,0 +1,191 @@
+use crate::connections::HandshakeInfo;
+use flare_core::flare_net::net::Platform;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{header, StatusCode};
+
+/// WebSocket 握手校验配置
+#[derive(Debug, Clone, Default)]
+pub struct WsHandshakeConfig {
+    /// 只接受该路径上的升级请求，None 时接受任意路径
+    pub path: Option<String>,
+    /// 允许的 Origin，为空时不校验
+    pub allowed_origins: Vec<String>,
+    /// 使用 `X-Forwarded-For` 中的第一个地址作为远程地址，仅在反向代理之后开启
+    pub trust_forwarded: bool,
+}
+
+impl WsHandshakeConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    pub fn trust_forwarded(mut self, trust: bool) -> Self {
+        self.trust_forwarded = trust;
+        self
+    }
+
+    /// 校验升级请求并读取握手信息
+    pub(crate) fn check(&self, req: &Request) -> std::result::Result<HandshakeInfo, (StatusCode, &'static str)> {
+        let header = |name: header::HeaderName| {
+            req.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+        };
+        let query = req.uri().query();
+        let info = HandshakeInfo {
+            path: req.uri().path().to_string(),
+            token: query_param(query, "token").or_else(|| bearer_token(header(header::AUTHORIZATION))),
+            platform: query_param(query, "platform").and_then(|v| parse_platform(&v)),
+            client_id: query_param(query, "client_id"),
+            user_agent: header(header::USER_AGENT),
+            origin: header(header::ORIGIN),
+            forwarded_for: header(header::HeaderName::from_static("x-forwarded-for")),
+        };
+
+        if let Some(path) = &self.path {
+            if &info.path != path {
+                return Err((StatusCode::NOT_FOUND, "not found"));
+            }
+        }
+        if !self.allowed_origins.is_empty() {
+            match &info.origin {
+                Some(origin) if self.allowed_origins.iter().any(|o| o == origin) => {}
+                _ => return Err((StatusCode::FORBIDDEN, "origin not allowed")),
+            }
+        }
+        Ok(info)
+    }
+
+    /// 计算连接的远程地址
+    pub(crate) fn remote_addr(&self, info: &HandshakeInfo, peer_addr: String) -> String {
+        if !self.trust_forwarded {
+            return peer_addr;
+        }
+        info.forwarded_for.as_deref()
+            .and_then(|v| v.split(',').next())
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+            .unwrap_or(peer_addr)
+    }
+}
+
+fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(query?.as_bytes())
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.into_owned())
+        .filter(|v| !v.is_empty())
+}
+
+/// 平台名不区分大小写，也接受枚举编号
+fn parse_platform(value: &str) -> Option<Platform> {
+    value.parse::<i32>().ok()
+        .and_then(|v| Platform::try_from(v).ok())
+        .or_else(|| Platform::from_str_name(&value.to_ascii_uppercase()))
+        .filter(|p| *p != Platform::Unknown)
+}
+
+fn bearer_token(value: Option<String>) -> Option<String> {
+    let value = value?;
+    let token = value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("bearer "))?.trim();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+fn reject(status: StatusCode, reason: &str) -> ErrorResponse {
+    let mut resp = ErrorResponse::new(Some(reason.to_string()));
+    *resp.status_mut() = status;
+    resp
+}
+
+/// 握手回调，校验通过后把握手信息写回 `info`
+// 错误类型由 tungstenite 的回调签名决定
+#[allow(clippy::result_large_err)]
+pub(crate) fn callback<'a>(
+    config: &'a WsHandshakeConfig,
+    info: &'a mut Option<HandshakeInfo>,
+) -> impl FnOnce(&Request, Response) -> std::result::Result<Response, ErrorResponse> + Unpin + 'a {
+    move |req, resp| {
+        *info = Some(config.check(req).map_err(|(status, reason)| reject(status, reason))?);
+        Ok(resp)
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::connections::ws::accept_ws;
+    use crate::server::server::Server;
+    use flare_core::flare_net::net::{Command, LoginResp, Message as ProtoMessage};
+    use futures::StreamExt;
+    use prost::Message;
+    use std::sync::Arc;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::http::HeaderValue;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    async fn upgrade(port: u16, uri: &str, origin: &str) -> Option<tokio_tungstenite::WebSocketStream<TcpStream>> {
+        let mut req = format!("ws://127.0.0.1:{}{}", port, uri).into_client_request().unwrap();
+        req.headers_mut().insert("origin", HeaderValue::from_str(origin).unwrap());
+        req.headers_mut().insert("user-agent", HeaderValue::from_static("flare-test"));
+        req.headers_mut().insert("x-forwarded-for", HeaderValue::from_static("10.0.0.1, 172.16.0.1"));
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        tokio_tungstenite::client_async(req, stream).await.ok().map(|(ws, _)| ws)
+    }
+
+    #[tokio::test]
+    async fn test_ws_handshake() {
+        let config = Arc::new(WsHandshakeConfig::new()
+            .path("/ws")
+            .allow_origin("https://app.example.com")
+            .trust_forwarded(true));
+        let server = Arc::new(Server::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (stream, remote) = listener.accept().await.unwrap();
+                let (config, server, tx) = (config.clone(), server.clone(), tx.clone());
+                tokio::spawn(async move {
+                    if let Ok(conn) = accept_ws(stream, remote.to_string(), None, &config).await {
+                        tx.send((conn.remote_addr().to_string(), conn.handshake().cloned())).unwrap();
+                        server.add_connection(conn).await;
+                    }
+                });
+            }
+        });
+
+        assert!(upgrade(port, "/other?token=t", "https://app.example.com").await.is_none());
+        assert!(upgrade(port, "/ws?token=t", "https://evil.example.com").await.is_none());
+
+        // 只有令牌没有平台时等待登录消息
+        let mut ws = upgrade(port, "/ws?token=t", "https://app.example.com").await.unwrap();
+        rx.recv().await.unwrap();
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(100), ws.next()).await.is_err());
+
+        // 握手携带令牌和平台，无需发送登录消息即完成认证
+        let mut ws = upgrade(port, "/ws?token=t%201&platform=web&client_id=c1", "https://app.example.com").await.unwrap();
+        let (remote_addr, info) = rx.recv().await.unwrap();
+        assert_eq!(remote_addr, "10.0.0.1");
+        let info = info.unwrap();
+        assert_eq!(info.path, "/ws");
+        assert_eq!(info.token.as_deref(), Some("t 1"));
+        assert_eq!((info.platform, info.client_id.as_deref()), (Some(Platform::Web), Some("c1")));
+        assert_eq!(info.user_agent.as_deref(), Some("flare-test"));
+
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(1), ws.next()).await.unwrap().unwrap().unwrap();
+        let WsMessage::Binary(data) = frame else { panic!("unexpected frame {:?}", frame) };
+        let msg = ProtoMessage::decode(data).unwrap();
+        assert_eq!(msg.command, Command::ServerResponse as i32);
+        assert_eq!(LoginResp::decode(&msg.data[..]).unwrap().user_id, "sss");
+    }
+}
@@ -1,3 +1,9 @@
 pub mod conn;
+pub mod handshake;
+pub mod tls;
 
-pub use conn::WsConnection;
\ No newline at end of file
+pub use conn::WsConnection;
+pub use handshake::WsHandshakeConfig;
+pub use tls::{accept_ws, create_ws_acceptor, WsTlsConfig};
+#[cfg(feature = "client")]
+pub use tls::{connect_ws, create_ws_connector};
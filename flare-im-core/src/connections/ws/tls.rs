@@ -0,0 +1,199 @@
+use crate::connections::quic_conf::{init_crypto, load_cert_chain, load_private_key};
+use crate::connections::{Connection, WsConnection};
+use crate::connections::ws::handshake::{callback, WsHandshakeConfig};
+use flare_core::error::{FlareErr, Result};
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsAcceptor;
+
+/// 创建 wss 服务端的 TLS 接收器，证书和私钥的读取方式与 QUIC 一致
+pub fn create_ws_acceptor(cert_path: &str, key_path: &str) -> anyhow::Result<TlsAcceptor> {
+    init_crypto()?;
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(load_cert_chain(cert_path)?, load_private_key(key_path)?)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// 完成 WebSocket 握手，设置了 TLS 接收器时先完成 TLS 握手
+///
+/// 升级请求按 `config` 校验路径和 Origin，握手信息保存在返回的连接上。
+pub async fn accept_ws(
+    stream: TcpStream,
+    remote_addr: String,
+    acceptor: Option<&TlsAcceptor>,
+    config: &WsHandshakeConfig,
+) -> Result<Box<dyn Connection>> {
+    let mut info = None;
+    let conn = match acceptor {
+        Some(acceptor) => {
+            let tls_stream = acceptor.accept(stream).await
+                .map_err(|e| FlareErr::ConnectionError(format!("TLS handshake failed: {}", e)))?;
+            let ws_stream = tokio_tungstenite::accept_hdr_async(tls_stream, callback(config, &mut info)).await?;
+            let info = info.unwrap_or_default();
+            let remote_addr = config.remote_addr(&info, remote_addr);
+            Box::new(WsConnection::new(ws_stream, remote_addr).with_handshake(info)) as Box<dyn Connection>
+        }
+        None => {
+            let ws_stream = tokio_tungstenite::accept_hdr_async(stream, callback(config, &mut info)).await?;
+            let info = info.unwrap_or_default();
+            let remote_addr = config.remote_addr(&info, remote_addr);
+            Box::new(WsConnection::new(ws_stream, remote_addr).with_handshake(info))
+        }
+    };
+    Ok(conn)
+}
+
+/// wss 客户端的证书校验配置
+#[derive(Debug, Clone)]
+pub struct WsTlsConfig {
+    /// 使用系统根证书
+    pub native_roots: bool,
+    /// 额外信任的 CA 证书文件
+    pub ca_cert_paths: Vec<String>,
+    /// 跳过证书校验，仅用于测试环境
+    pub insecure: bool,
+}
+
+impl Default for WsTlsConfig {
+    fn default() -> Self {
+        Self {
+            native_roots: true,
+            ca_cert_paths: Vec::new(),
+            insecure: false,
+        }
+    }
+}
+
+impl WsTlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn native_roots(mut self, enabled: bool) -> Self {
+        self.native_roots = enabled;
+        self
+    }
+
+    pub fn ca_cert(mut self, path: impl Into<String>) -> Self {
+        self.ca_cert_paths.push(path.into());
+        self
+    }
+
+    pub fn insecure(mut self, insecure: bool) -> Self {
+        self.insecure = insecure;
+        self
+    }
+}
+
+#[cfg(feature = "client")]
+mod client {
+    use super::*;
+    use crate::connections::quic::config::SkipServerVerification;
+    use log::warn;
+    use rustls::pki_types::ServerName;
+    use rustls::RootCertStore;
+    use tokio_rustls::TlsConnector;
+
+    /// 创建 wss 客户端的 TLS 连接器
+    pub fn create_ws_connector(config: &WsTlsConfig) -> anyhow::Result<TlsConnector> {
+        init_crypto()?;
+        let mut roots = RootCertStore::empty();
+        if config.native_roots {
+            let native = rustls_native_certs::load_native_certs();
+            for e in &native.errors {
+                warn!("Failed to load native root certificate: {}", e);
+            }
+            roots.add_parsable_certificates(native.certs);
+        }
+        for path in &config.ca_cert_paths {
+            for cert in load_cert_chain(path)? {
+                roots.add(cert)?;
+            }
+        }
+        let mut tls = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        if config.insecure {
+            tls.dangerous().set_certificate_verifier(Arc::new(SkipServerVerification));
+        }
+        Ok(TlsConnector::from(Arc::new(tls)))
+    }
+
+    /// 连接 WebSocket 服务端，`wss://` 地址按 `tls` 配置校验证书
+    pub async fn connect_ws(url: &str, tls: &WsTlsConfig) -> Result<Box<dyn Connection>> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| FlareErr::ConnectionError(format!("Invalid WebSocket URL: {}", e)))?;
+        if parsed.scheme() != "wss" {
+            let (ws_stream, _) = tokio_tungstenite::connect_async(url).await
+                .map_err(|e| FlareErr::ConnectionError(format!("WebSocket connection failed: {}", e)))?;
+            return Ok(Box::new(WsConnection::new(ws_stream, url.to_string())));
+        }
+
+        let host = parsed.host_str()
+            .ok_or_else(|| FlareErr::ConnectionError("WebSocket URL has no host".to_string()))?
+            .to_string();
+        let port = parsed.port_or_known_default().unwrap_or(443);
+        let server_name = ServerName::try_from(host.clone())
+            .map_err(|e| FlareErr::ConnectionError(format!("Invalid server name {}: {}", host, e)))?;
+        let connector = create_ws_connector(tls)
+            .map_err(|e| FlareErr::ConnectionError(format!("Invalid TLS config: {}", e)))?;
+
+        let stream = TcpStream::connect((host.as_str(), port)).await
+            .map_err(|e| FlareErr::ConnectionError(format!("WebSocket connection failed: {}", e)))?;
+        let tls_stream = connector.connect(server_name, stream).await
+            .map_err(|e| FlareErr::ConnectionError(format!("TLS handshake failed: {}", e)))?;
+        let (ws_stream, _) = tokio_tungstenite::client_async(url, tls_stream).await
+            .map_err(|e| FlareErr::ConnectionError(format!("WebSocket connection failed: {}", e)))?;
+        Ok(Box::new(WsConnection::new(ws_stream, url.to_string())))
+    }
+}
+
+#[cfg(feature = "client")]
+pub use client::{connect_ws, create_ws_connector};
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use flare_core::flare_net::net::{Command, Message as ProtoMessage};
+    use tokio::net::TcpListener;
+
+    const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/certs/cert.pem");
+    const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/certs/key.pem");
+
+    #[tokio::test]
+    async fn test_wss_round_trip() {
+        let acceptor = create_ws_acceptor(CERT, KEY).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, remote) = listener.accept().await.unwrap();
+            let conn = accept_ws(stream, remote.to_string(), Some(&acceptor), &WsHandshakeConfig::default()).await.unwrap();
+            let msg = conn.receive().await.unwrap();
+            conn.send(msg).await.unwrap();
+        });
+
+        // 仓库中的测试证书为自签名证书，跳过校验只验证 TLS 通道
+        let url = format!("wss://localhost:{}", addr.port());
+        let tls = WsTlsConfig::new().native_roots(false).insecure(true);
+        let conn = connect_ws(&url, &tls).await.unwrap();
+        conn.send(ProtoMessage {
+            command: Command::Ping as i32,
+            data: b"tls".to_vec(),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(conn.receive().await.unwrap().data, b"tls".to_vec());
+        server.await.unwrap();
+
+        // 不信任自签名证书时握手失败
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = create_ws_acceptor(CERT, KEY).unwrap();
+        tokio::spawn(async move {
+            let (stream, remote) = listener.accept().await.unwrap();
+            let _ = accept_ws(stream, remote.to_string(), Some(&acceptor), &WsHandshakeConfig::default()).await;
+        });
+        let url = format!("wss://localhost:{}", addr.port());
+        assert!(connect_ws(&url, &WsTlsConfig::new().native_roots(false)).await.is_err());
+    }
+}
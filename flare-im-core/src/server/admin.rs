@@ -0,0 +1,191 @@
+use crate::server::auth_handler::AuthHandler;
+use crate::server::server::{ConnectionInfo, Server};
+use crate::server::server_handler::ServerHandler;
+use crate::server::sys_handler::SystemHandler;
+use async_trait::async_trait;
+use flare_core::error::Result;
+use flare_core::flare_net::net::Message as ProtoMessage;
+use flare_rpc_core::admin::proto::{ConnectionDetail, ConnectionList, SessionRoute, StatsResponse, UserSessionsResponse};
+use flare_rpc_core::admin::{AdminHandler, GatewayAdminServer, GatewayAdminService};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// 踢下线时未指定原因使用的提示
+const DEFAULT_KICK_REASON: &str = "Kicked by administrator";
+
+/// 基于 flare-rpc-core 的网关管理处理器，查询和管理本节点上的连接
+pub struct ServerAdmin<S, A, Y>
+where
+    S: ServerHandler + Send + Sync + 'static,
+    A: AuthHandler + Send + Sync + 'static,
+    Y: SystemHandler + Send + Sync + 'static,
+{
+    server: Arc<Server<S, A, Y>>,
+}
+
+impl<S, A, Y> ServerAdmin<S, A, Y>
+where
+    S: ServerHandler + Send + Sync + 'static,
+    A: AuthHandler + Send + Sync + 'static,
+    Y: SystemHandler + Send + Sync + 'static,
+{
+    pub fn new(server: Arc<Server<S, A, Y>>) -> Self {
+        Self { server }
+    }
+
+    /// 创建可添加到 tonic server 的网关管理服务
+    pub fn into_server(self) -> GatewayAdminServer<GatewayAdminService<Self>> {
+        GatewayAdminService::new(self).into_server()
+    }
+}
+
+async fn detail(info: &ConnectionInfo) -> ConnectionDetail {
+    ConnectionDetail {
+        conn_id: info.get_conn_id(),
+        user_id: info.get_user_id(),
+        platform: info.get_platform().as_str_name().to_string(),
+        protocol: info.get_protocol(),
+        client_id: info.get_client_id(),
+        remote_addr: info.get_remote_addr(),
+        language: info.get_language().unwrap_or_default(),
+        background: info.is_background().await,
+        connected_at: info.get_connection_at().timestamp_millis(),
+        last_heartbeat: info.get_last_heartbeat().await.timestamp_millis(),
+    }
+}
+
+#[async_trait]
+impl<S, A, Y> AdminHandler for ServerAdmin<S, A, Y>
+where
+    S: ServerHandler + Send + Sync + 'static,
+    A: AuthHandler + Send + Sync + 'static,
+    Y: SystemHandler + Send + Sync + 'static,
+{
+    async fn list_connections(&self, offset: usize, limit: usize) -> Result<ConnectionList> {
+        let conns = self.server.get_connections().await;
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let mut connections = Vec::new();
+        for info in conns.iter().skip(offset).take(limit) {
+            connections.push(detail(info).await);
+        }
+        Ok(ConnectionList {
+            total: conns.len() as u32,
+            connections,
+        })
+    }
+
+    async fn user_sessions(&self, user_id: &str) -> Result<UserSessionsResponse> {
+        let mut connections = Vec::new();
+        for info in self.server.get_user_connections(user_id).await {
+            connections.push(detail(&info).await);
+        }
+        let routes = self.server.lookup_sessions(user_id).await?
+            .into_iter()
+            .map(|route| SessionRoute {
+                node_id: route.node_id,
+                conn_id: route.conn_id,
+                platform: route.platform.as_str_name().to_string(),
+            })
+            .collect();
+        Ok(UserSessionsResponse { connections, routes })
+    }
+
+    async fn kick(&self, user_id: &str, conn_id: Option<&str>, reason: &str) -> Result<u32> {
+        let reason = if reason.is_empty() { DEFAULT_KICK_REASON } else { reason };
+        Ok(self.server.kick(user_id, conn_id, reason).await as u32)
+    }
+
+    async fn push_message(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        self.server.send_to_user(user_id, msg).await
+    }
+
+    async fn stats(&self) -> Result<StatsResponse> {
+        let conns = self.server.get_connections().await;
+        let mut stats = StatsResponse {
+            connections: conns.len() as u32,
+            ..Default::default()
+        };
+        let mut users = HashSet::new();
+        for info in &conns {
+            users.insert(info.get_user_id());
+            *stats.by_protocol.entry(info.get_protocol()).or_default() += 1;
+            *stats.by_platform.entry(info.get_platform().as_str_name().to_string()).or_default() += 1;
+        }
+        stats.users = users.len() as u32;
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock::{self, DEFAULT_USER};
+    use flare_core::flare_net::net::{Command, LoginReq, Platform};
+    use flare_rpc_core::admin::proto::{KickRequest, ListConnectionsRequest, PushMessageRequest, StatsRequest, UserSessionsRequest};
+    use flare_rpc_core::admin::GatewayAdminClient;
+    use prost::Message;
+    use std::time::Duration;
+    use tonic::{Code, Request};
+
+    /// 携带管理令牌的请求
+    fn authed<T>(msg: T) -> Request<T> {
+        let mut req = Request::new(msg);
+        req.metadata_mut().insert("authorization", "Bearer secret".parse().unwrap());
+        req
+    }
+
+    #[tokio::test]
+    async fn test_admin_service() {
+        let server = Arc::new(Server::default());
+        let mut ios = mock::login(server.clone(), LoginReq { platform: Platform::Ios as i32, ..mock::login_req() }).await;
+        let _web = mock::login(server.clone(), LoginReq { platform: Platform::Web as i32, ..mock::login_req() }).await;
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let service = GatewayAdminService::new(ServerAdmin::new(server.clone())).with_token("secret");
+        tokio::spawn(service.serve(addr, async { let _ = stop_rx.await; }));
+        let mut client = None;
+        for _ in 0..50 {
+            if let Ok(c) = GatewayAdminClient::connect(format!("http://127.0.0.1:{}", port)).await {
+                client = Some(c);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mut client = client.expect("admin service");
+
+        // 未携带令牌的请求被拒绝
+        let err = client.kick(KickRequest { user_id: DEFAULT_USER.into(), ..Default::default() }).await.unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+        assert_eq!(server.get_user_connections(DEFAULT_USER).await.len(), 2);
+
+        let stats = client.stats(authed(StatsRequest {})).await.unwrap().into_inner();
+        assert_eq!((stats.connections, stats.users), (2, 1));
+        assert_eq!(stats.by_platform.get("IOS"), Some(&1));
+
+        let list = client.list_connections(authed(ListConnectionsRequest { offset: 1, limit: 10 })).await.unwrap().into_inner();
+        assert_eq!(list.total, 2);
+        assert_eq!(list.connections.len(), 1);
+        assert_eq!(list.connections[0].platform, "WEB");
+
+        let sessions = client.user_sessions(authed(UserSessionsRequest { user_id: DEFAULT_USER.into() })).await.unwrap().into_inner();
+        assert_eq!(sessions.connections.len(), 2);
+        assert_eq!(sessions.routes.len(), 2);
+        let ios_conn = sessions.connections.iter().find(|c| c.platform == "IOS").unwrap().conn_id.clone();
+
+        let msg = ProtoMessage { command: Command::ServerPushMsg as i32, data: b"hello".to_vec(), ..Default::default() };
+        client.push_message(authed(PushMessageRequest { user_id: DEFAULT_USER.into(), message: msg.encode_to_vec() })).await.unwrap();
+        let pushed = ios.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(pushed.data, b"hello");
+
+        // 只踢掉指定连接
+        let kicked = client.kick(authed(KickRequest { user_id: DEFAULT_USER.into(), conn_id: ios_conn, reason: String::new() })).await.unwrap().into_inner();
+        assert_eq!(kicked.kicked, 1);
+        let notice = ios.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(notice.command, Command::KickOnline as i32);
+        assert_eq!(notice.data, DEFAULT_KICK_REASON.as_bytes());
+        assert_eq!(server.get_user_connections(DEFAULT_USER).await.len(), 1);
+        let _ = stop_tx.send(());
+    }
+}
@@ -93,6 +93,7 @@ impl AuthHandler for DefAuthHandler {
         let resp = LoginResp {
             user_id:"sss".to_string(),
             language:"zh".to_string(),
+            ..Default::default()
         };
         Ok(Response {
             code: ResCode::Success as i32,
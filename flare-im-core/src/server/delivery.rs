@@ -0,0 +1,230 @@
+use flare_core::flare_net::net::Message as ProtoMessage;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+/// 可靠投递配置
+#[derive(Debug, Clone)]
+pub struct DeliveryConfig {
+    /// 等待 ACK 的超时时间
+    pub ack_timeout: Duration,
+    /// 超时后的最大重试次数
+    pub max_retries: u32,
+    /// 每次重试后超时时间的倍数
+    pub backoff_factor: u32,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            ack_timeout: Duration::from_secs(5),
+            max_retries: 3,
+            backoff_factor: 2,
+        }
+    }
+}
+
+impl DeliveryConfig {
+    /// 第 `attempt` 次等待的超时时间，从 0 开始
+    pub fn timeout_for(&self, attempt: u32) -> Duration {
+        self.ack_timeout * self.backoff_factor.max(1).saturating_pow(attempt)
+    }
+}
+
+/// 重试后仍未确认的消息
+#[derive(Debug, Clone)]
+pub struct Undelivered {
+    pub user_id: String,
+    pub msg: ProtoMessage,
+}
+
+/// server_msg_id -> (user_id, ack)
+type PendingAcks = Arc<Mutex<HashMap<String, (String, oneshot::Sender<()>)>>>;
+
+/// 投递跟踪器，记录等待客户端 ACK 的服务端消息
+#[derive(Clone)]
+pub struct DeliveryTracker {
+    config: DeliveryConfig,
+    pending: PendingAcks,
+    undelivered: mpsc::UnboundedSender<Undelivered>,
+}
+
+impl DeliveryTracker {
+    pub fn new(config: DeliveryConfig) -> (Self, mpsc::UnboundedReceiver<Undelivered>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                config,
+                pending: Arc::new(Mutex::new(HashMap::new())),
+                undelivered: tx,
+            },
+            rx,
+        )
+    }
+
+    /// 使用新的配置，共享等待队列和上报通道
+    pub fn with_config(&self, config: DeliveryConfig) -> Self {
+        Self {
+            config,
+            pending: self.pending.clone(),
+            undelivered: self.undelivered.clone(),
+        }
+    }
+
+    pub fn config(&self) -> &DeliveryConfig {
+        &self.config
+    }
+
+    /// 生成服务端消息ID
+    pub fn next_msg_id() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+
+    /// 登记一条等待 ACK 的消息，返回 ACK 到达时完成的接收端
+    pub async fn register(&self, user_id: &str, msg_id: &str) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(msg_id.to_string(), (user_id.to_string(), tx));
+        rx
+    }
+
+    /// 处理客户端 ACK，返回是否命中等待中的消息
+    pub async fn ack(&self, user_id: &str, msg_id: &str) -> bool {
+        let mut pending = self.pending.lock().await;
+        match pending.get(msg_id) {
+            Some((owner, _)) if owner == user_id => {
+                if let Some((_, tx)) = pending.remove(msg_id) {
+                    let _ = tx.send(());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 放弃等待并上报投递失败
+    pub async fn give_up(&self, user_id: &str, msg: ProtoMessage) {
+        self.pending.lock().await.remove(&msg.server_msg_id);
+        let _ = self.undelivered.send(Undelivered {
+            user_id: user_id.to_string(),
+            msg,
+        });
+    }
+
+    /// 等待中的消息数量
+    pub async fn pending_count(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock::{self, DEFAULT_USER};
+    use crate::server::auth_handler::{AuthCommandHandler, DefAuthHandler};
+    use crate::server::handlers::ServerMessageHandler;
+    use crate::server::server::Server;
+    use crate::server::server_handler::{ServerCommandHandler, ServerHandler};
+    use crate::server::sys_handler::{DefSystemHandler, SystemCommandHandler};
+    use async_trait::async_trait;
+    use flare_core::context::AppContext;
+    use flare_core::error::Result;
+    use flare_core::flare_net::net::{Command, Response};
+
+    #[derive(Clone, Default)]
+    struct RecordHandler {
+        undelivered: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    #[async_trait]
+    impl ServerHandler for RecordHandler {
+        async fn handle_send_message(&self, _ctx: &AppContext) -> Result<Response> {
+            Ok(Response::default())
+        }
+
+        async fn handle_pull_message(&self, _ctx: &AppContext) -> Result<Response> {
+            Ok(Response::default())
+        }
+
+        async fn handle_request(&self, _ctx: &AppContext) -> Result<Response> {
+            Ok(Response::default())
+        }
+
+        async fn handle_ack(&self, _ctx: &AppContext) -> Result<Response> {
+            Ok(Response::default())
+        }
+
+        async fn handle_undelivered(&self, user_id: &str, msg: &ProtoMessage) -> Result<()> {
+            self.undelivered.lock().await.push((user_id.to_string(), msg.server_msg_id.clone()));
+            Ok(())
+        }
+    }
+
+    fn server(handler: RecordHandler) -> Arc<Server<RecordHandler, DefAuthHandler, DefSystemHandler>> {
+        let handler = ServerMessageHandler::new(
+            AuthCommandHandler::new(DefAuthHandler::new()),
+            ServerCommandHandler::new(handler),
+            SystemCommandHandler::new(DefSystemHandler::new()),
+        );
+        Arc::new(Server::new(handler).with_delivery_config(DeliveryConfig {
+            ack_timeout: Duration::from_millis(50),
+            max_retries: 2,
+            backoff_factor: 1,
+        }))
+    }
+
+    fn push() -> ProtoMessage {
+        ProtoMessage {
+            command: Command::ServerPushMsg as i32,
+            data: b"hello".to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redeliver_until_undelivered() {
+        let handler = RecordHandler::default();
+        let server = server(handler.clone());
+        let mut peer = mock::login_default(server.clone()).await;
+
+        let msg_id = server.send_reliable(DEFAULT_USER, push()).await.unwrap();
+        // 首次投递 + 2 次重试
+        for _ in 0..3 {
+            let msg = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+            assert_eq!(msg.server_msg_id, msg_id);
+        }
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(*handler.undelivered.lock().await, vec![(DEFAULT_USER.to_string(), msg_id)]);
+        assert!(peer.recv_timeout(Duration::from_millis(100)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ack_stops_redelivery() {
+        let handler = RecordHandler::default();
+        let server = server(handler.clone());
+        let mut peer = mock::login_default(server.clone()).await;
+
+        let msg_id = server.send_reliable(DEFAULT_USER, push()).await.unwrap();
+        let msg = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        peer.tx.send(ProtoMessage {
+            command: Command::ClientAck as i32,
+            data: msg.server_msg_id.into_bytes(),
+            ..Default::default()
+        }).unwrap();
+
+        // ACK 的响应之外不应再收到重发
+        let resp = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(resp.command, Command::ServerResponse as i32);
+        assert!(peer.recv_timeout(Duration::from_millis(200)).await.is_none());
+        assert!(handler.undelivered.lock().await.is_empty());
+        assert!(!msg_id.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_offline_user_is_undelivered() {
+        let handler = RecordHandler::default();
+        let server = server(handler.clone());
+        let msg_id = server.send_reliable("nobody", push()).await.unwrap();
+        assert_eq!(*handler.undelivered.lock().await, vec![("nobody".to_string(), msg_id)]);
+    }
+}
@@ -2,9 +2,13 @@ use flare_core::context::AppContext;
 use flare_core::error::{FlareErr, Result};
 use async_trait::async_trait;
 use log::debug;
-use flare_core::flare_net::net::{Command, ResCode, Response};
+use flare_core::flare_net::net::{Command, Message as ProtoMessage, ResCode, Response};
 
+use std::sync::Arc;
 use crate::server::auth_handler::{AuthCommandHandler, AuthHandler};
+use crate::server::lifecycle::DisconnectReason;
+use crate::server::middleware::{Middleware, Next};
+use crate::server::router::Router;
 use crate::server::server::ConnectionInfo;
 use crate::server::server_handler::{ ServerCommandHandler, ServerHandler};
 use crate::server::sys_handler::{SystemCommandHandler, SystemHandler};
@@ -34,6 +38,8 @@ where
     auth_handler: AuthCommandHandler<A>,
     server_handler: ServerCommandHandler<S>,
     system_handler: SystemCommandHandler<Y>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    router: Router,
 }
 
 impl<S, A, Y> ServerMessageHandler<S, A, Y>
@@ -51,9 +57,33 @@ where
             auth_handler,
             server_handler,
             system_handler,
+            middlewares: Vec::new(),
+            router: Router::new(),
         }
     }
 
+    /// 设置业务路由，带有已注册 `route` 的消息优先交给路由处理
+    pub fn with_routes(mut self, router: Router) -> Self {
+        self.router = router;
+        self
+    }
+
+    /// 设置业务路由
+    pub fn set_routes(&mut self, router: Router) {
+        self.router = router;
+    }
+
+    /// 添加中间件，先添加的在外层
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// 添加中间件，先添加的在外层
+    pub fn add_middleware(&mut self, middleware: impl Middleware + 'static) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
     /// 根据命令选择合适的处理器
     fn get_handler(&self, command: Command) -> Result<&dyn CommandHandler> {
         if self.auth_handler.supports_command(command) {
@@ -73,10 +103,24 @@ where
     pub async fn handle_new_connection(&self, ctx:  &AppContext, conn: &ConnectionInfo) -> Result<Response> {
         self.system_handler.handle_new_connection(ctx, conn).await
     }
+    /// 连接生命周期回调
+    pub async fn on_connected(&self, conn_id: &str, remote_addr: &str) {
+        self.system_handler.on_connected(conn_id, remote_addr).await
+    }
+    pub async fn on_authenticated(&self, conn: &ConnectionInfo) {
+        self.system_handler.on_authenticated(conn).await
+    }
+    pub async fn on_disconnected(&self, conn: &ConnectionInfo, reason: DisconnectReason) {
+        self.system_handler.on_disconnected(conn, reason).await
+    }
     /// 认证
     pub async fn handle_auth(&self, ctx:  &AppContext) -> Result<Response> {
         self.auth_handler.handle_login(ctx).await
     }
+    /// 可靠推送投递失败
+    pub async fn handle_undelivered(&self, user_id: &str, msg: &ProtoMessage) -> Result<()> {
+        self.server_handler.handle_undelivered(user_id, msg).await
+    }
   
 }
 
@@ -106,9 +150,14 @@ where
                 data: Vec::new(),
             }),
             _ => {
-                // 根据命令选择处理器
-                let handler = self.get_handler(command)?;
-                handler.handle_command(ctx).await
+                // 业务请求和消息优先按路由分发，否则根据命令选择处理器，经过中间件链处理。
+                // 其他命令忽略路由，客户端不能借路由绕过系统处理器
+                let routable = matches!(command, Command::ClientRequest | Command::ClientSendMessage);
+                let handler: &dyn CommandHandler = match ctx.route() {
+                    Some(route) if routable && self.router.has_route(&route) => &self.router,
+                    _ => self.get_handler(command)?,
+                };
+                Next::new(&self.middlewares, handler).run(ctx).await
             }
         }
     }
@@ -163,4 +212,73 @@ mod tests {
         assert_eq!(response.code, ResCode::Success as i32);
         assert_eq!(response.message, "PONG");
     }
+
+    #[tokio::test]
+    async fn test_handler_panic_isolated() {
+        use crate::connections::mock;
+        use crate::server::router::Router;
+        use crate::server::server::Server;
+        use flare_core::flare_net::net::{LoginReq, LoginResp};
+        use prost::Message;
+        use std::time::Duration;
+
+        let router = Router::new()
+            .route("panic", |_ctx: AppContext, _req: LoginReq| async move {
+                if true {
+                    panic!("boom");
+                }
+                Ok(LoginResp::default())
+            })
+            .route("timeout", |_ctx: AppContext, _req: LoginReq| async move {
+                Err::<LoginResp, _>(FlareErr::timeout("upstream"))
+            });
+        let server = std::sync::Arc::new(Server::default().with_routes(router));
+        let mut peer = mock::login_default(server).await;
+
+        for (route, code) in [("panic", ResCode::InternalError), ("timeout", ResCode::Timeout)] {
+            peer.tx.send(ProtoMessage {
+                command: Command::ClientRequest as i32,
+                route: route.into(),
+                ..Default::default()
+            }).unwrap();
+            // panic 之后连接仍然可用
+            let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+            assert_eq!(Response::decode(&reply.data[..]).unwrap().code, code as i32);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_panic_isolated() {
+        use crate::connections::mock;
+        use crate::server::server::Server;
+        use prost::Message;
+        use std::time::Duration;
+
+        struct PanicAuth;
+
+        #[async_trait]
+        impl AuthHandler for PanicAuth {
+            async fn handle_login(&self, _ctx: &AppContext) -> Result<Response> {
+                panic!("auth boom");
+            }
+
+            async fn handle_logout(&self, _ctx: &AppContext) -> Result<Response> {
+                Ok(Response::default())
+            }
+        }
+
+        let server = std::sync::Arc::new(Server::new(ServerMessageHandler::new(
+            AuthCommandHandler::new(PanicAuth),
+            ServerCommandHandler::new(DefServerHandler),
+            SystemCommandHandler::new(DefSystemHandler),
+        )));
+        let (conn, mut peer) = mock::pair();
+        peer.tx.send(ProtoMessage { command: Command::Login as i32, ..Default::default() }).unwrap();
+        let task = tokio::spawn(async move { server.add_connection(Box::new(conn)).await });
+
+        // 认证处理器 panic 时回复内部错误，连接任务正常结束
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(Response::decode(&reply.data[..]).unwrap().code, ResCode::InternalError as i32);
+        assert!(task.await.is_ok());
+    }
 }
@@ -0,0 +1,153 @@
+use flare_core::flare_net::net::{LoginResp, Platform};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+// 移动端切到后台后系统会限制网络活动，心跳间隔放宽到 NAT 超时以内
+const MOBILE_BACKGROUND_INTERVAL: Duration = Duration::from_secs(270);
+const DEFAULT_TIMEOUT_FACTOR: u32 = 3;
+
+/// 前后台的心跳间隔
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatIntervals {
+    pub foreground: Duration,
+    pub background: Duration,
+}
+
+/// 心跳策略，按平台和前后台状态决定心跳间隔，默认移动端后台放宽到 270 秒
+///
+/// 登录响应中下发给客户端，连接超过 `间隔 * timeout_factor` 没有消息时断开。
+/// 间隔为 0 时使用默认的 30 秒
+#[derive(Debug, Clone)]
+pub struct HeartbeatPolicy {
+    default: HeartbeatIntervals,
+    platforms: HashMap<Platform, HeartbeatIntervals>,
+    timeout_factor: u32,
+}
+
+impl HeartbeatIntervals {
+    fn new(foreground: Duration, background: Duration) -> Self {
+        let non_zero = |d: Duration| if d.is_zero() { DEFAULT_INTERVAL } else { d };
+        Self {
+            foreground: non_zero(foreground),
+            background: non_zero(background),
+        }
+    }
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        [Platform::Ios, Platform::Android, Platform::Apad, Platform::Ipad]
+            .into_iter()
+            .fold(Self::new(DEFAULT_INTERVAL, DEFAULT_INTERVAL), |policy, p| {
+                policy.platform(p, DEFAULT_INTERVAL, MOBILE_BACKGROUND_INTERVAL)
+            })
+    }
+}
+
+impl HeartbeatPolicy {
+    /// 所有平台使用相同的心跳间隔
+    pub fn new(foreground: Duration, background: Duration) -> Self {
+        Self {
+            default: HeartbeatIntervals::new(foreground, background),
+            platforms: HashMap::new(),
+            timeout_factor: DEFAULT_TIMEOUT_FACTOR,
+        }
+    }
+
+    /// 设置平台的心跳间隔
+    pub fn platform(mut self, platform: Platform, foreground: Duration, background: Duration) -> Self {
+        self.platforms.insert(platform, HeartbeatIntervals::new(foreground, background));
+        self
+    }
+
+    /// 允许连续丢失的心跳次数
+    pub fn timeout_factor(mut self, factor: u32) -> Self {
+        self.timeout_factor = factor.max(1);
+        self
+    }
+
+    pub fn intervals(&self, platform: Platform) -> HeartbeatIntervals {
+        self.platforms.get(&platform).copied().unwrap_or(self.default)
+    }
+
+    pub fn interval(&self, platform: Platform, background: bool) -> Duration {
+        let intervals = self.intervals(platform);
+        if background { intervals.background } else { intervals.foreground }
+    }
+
+    /// 连接超时时间
+    pub fn timeout(&self, platform: Platform, background: bool) -> Duration {
+        self.interval(platform, background) * self.timeout_factor
+    }
+
+    /// 超时检测周期，取所有间隔中的最小值
+    pub fn check_period(&self) -> Duration {
+        std::iter::once(&self.default)
+            .chain(self.platforms.values())
+            .flat_map(|i| [i.foreground, i.background])
+            .min()
+            .unwrap_or(DEFAULT_INTERVAL)
+    }
+
+    /// 把心跳间隔写入登录响应
+    pub(crate) fn apply(&self, resp: &mut LoginResp, platform: Platform) {
+        let intervals = self.intervals(platform);
+        resp.heartbeat_interval_ms = intervals.foreground.as_millis() as u32;
+        resp.background_heartbeat_interval_ms = intervals.background.as_millis() as u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock;
+    use crate::server::lifecycle::{ConnectionEvent, DisconnectReason};
+    use crate::server::server::Server;
+    use flare_core::flare_net::net::{Command, LoginReq, Message as ProtoMessage};
+    use prost::Message;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_heartbeat_policy() {
+        let policy = HeartbeatPolicy::default();
+        assert_eq!(policy.interval(Platform::Web, true), DEFAULT_INTERVAL);
+        assert_eq!(policy.interval(Platform::Ios, true), MOBILE_BACKGROUND_INTERVAL);
+        assert_eq!(policy.timeout(Platform::Ios, false), DEFAULT_INTERVAL * 3);
+        // 间隔为 0 时使用默认值，检测周期不会为 0
+        let policy = HeartbeatPolicy::new(Duration::ZERO, Duration::from_secs(60));
+        assert_eq!(policy.check_period(), DEFAULT_INTERVAL);
+
+        let policy = HeartbeatPolicy::new(Duration::from_millis(50), Duration::from_secs(60))
+            .platform(Platform::Android, Duration::from_millis(40), Duration::from_secs(120))
+            .timeout_factor(2);
+        assert_eq!(policy.check_period(), Duration::from_millis(40));
+        let server = Arc::new(Server::default().with_heartbeat_policy(policy));
+        let mut events = server.subscribe_events();
+
+        // 登录响应下发平台对应的心跳间隔
+        let (conn, mut peer) = mock::pair();
+        peer.tx.send(ProtoMessage {
+            command: Command::Login as i32,
+            data: LoginReq { platform: Platform::Android as i32, ..mock::login_req() }.encode_to_vec(),
+            ..Default::default()
+        }).unwrap();
+        let handle = server.clone();
+        tokio::spawn(async move { handle.add_connection(Box::new(conn)).await });
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        let resp = LoginResp::decode(&reply.data[..]).unwrap();
+        assert_eq!(resp.heartbeat_interval_ms, 40);
+        assert_eq!(resp.background_heartbeat_interval_ms, 120_000);
+
+        // 前台不发心跳时超时断开
+        let reason = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                if let ConnectionEvent::Disconnected { reason, .. } = events.recv().await.unwrap() {
+                    return reason;
+                }
+            }
+        }).await.unwrap();
+        assert_eq!(reason, DisconnectReason::Timeout);
+        drop(peer);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::server::server::ConnectionInfo;
+
+/// 事件通道容量，订阅者处理过慢时丢弃最旧的事件
+pub const EVENT_CAPACITY: usize = 1024;
+
+/// 连接断开原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// 心跳超时
+    Timeout,
+    /// 客户端主动关闭
+    ClientClose,
+    /// 被服务端踢下线，包括登录策略顶替和限流断开
+    Kicked,
+    /// 连接或协议错误
+    Error,
+    /// 服务端停机
+    Shutdown,
+}
+
+/// 连接生命周期事件，通过 `Server::subscribe_events` 订阅
+#[derive(Clone)]
+pub enum ConnectionEvent {
+    /// 建立传输连接，尚未认证
+    Connected {
+        conn_id: String,
+        remote_addr: String,
+        protocol: String,
+    },
+    /// 认证完成并注册到用户连接表
+    Authenticated(ConnectionInfo),
+    /// 已从连接表中移除
+    Disconnected {
+        info: ConnectionInfo,
+        reason: DisconnectReason,
+    },
+}
+
+impl ConnectionEvent {
+    pub fn conn_id(&self) -> String {
+        match self {
+            Self::Connected { conn_id, .. } => conn_id.clone(),
+            Self::Authenticated(info) | Self::Disconnected { info, .. } => info.get_conn_id(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock::{self, DEFAULT_USER};
+    use crate::server::auth_handler::{AuthCommandHandler, DefAuthHandler};
+    use crate::server::handlers::ServerMessageHandler;
+    use crate::server::login_policy::LoginPolicy;
+    use crate::server::server::Server;
+    use crate::server::server_handler::{DefServerHandler, ServerCommandHandler};
+    use crate::server::sys_handler::SystemCommandHandler;
+    use flare_core::flare_net::net::LoginReq;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    async fn next(events: &mut broadcast::Receiver<ConnectionEvent>) -> ConnectionEvent {
+        tokio::time::timeout(Duration::from_secs(1), events.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_events() {
+        let server = Arc::new(Server::default().with_login_policy(LoginPolicy::OnePerPlatform));
+        let mut events = server.subscribe_events();
+        let req = mock::login_req();
+
+        let _first = mock::login(server.clone(), req.clone()).await;
+        let ConnectionEvent::Connected { conn_id: first_id, .. } = next(&mut events).await else { panic!("expected connected") };
+        let ConnectionEvent::Authenticated(info) = next(&mut events).await else { panic!("expected authenticated") };
+        assert_eq!(info.get_conn_id(), first_id);
+        assert_eq!(info.get_user_id(), DEFAULT_USER);
+
+        // 同平台再次登录，旧连接被踢下线
+        let second = mock::login(server.clone(), req).await;
+        let second_id = next(&mut events).await.conn_id();
+        assert!(matches!(next(&mut events).await, ConnectionEvent::Authenticated(_)));
+        match next(&mut events).await {
+            ConnectionEvent::Disconnected { info, reason } => {
+                assert_eq!(info.get_conn_id(), first_id);
+                assert_eq!(reason, DisconnectReason::Kicked);
+            }
+            _ => panic!("expected disconnected"),
+        }
+
+        // 客户端关闭
+        drop(second);
+        match next(&mut events).await {
+            ConnectionEvent::Disconnected { info, reason } => {
+                assert_eq!(info.get_conn_id(), second_id);
+                assert_eq!(reason, DisconnectReason::ClientClose);
+            }
+            _ => panic!("expected disconnected"),
+        }
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_connection_removed() {
+        let server = Arc::new(Server::new(ServerMessageHandler::new(
+            AuthCommandHandler::new(DefAuthHandler::new()),
+            ServerCommandHandler::new(DefServerHandler::new()),
+            SystemCommandHandler::new(mock::RejectNew),
+        )).with_resume_window(Duration::from_secs(60)));
+        let mut events = server.subscribe_events();
+        let (_peer, resp) = mock::login_with_resp(server.clone(), mock::login_req()).await;
+
+        // 初始化失败的连接立即移除，不回调认证事件
+        assert!(matches!(next(&mut events).await, ConnectionEvent::Connected { .. }));
+        match next(&mut events).await {
+            ConnectionEvent::Disconnected { reason, .. } => assert_eq!(reason, DisconnectReason::Error),
+            _ => panic!("expected disconnected"),
+        }
+        assert!(server.get_connections().await.is_empty());
+        // 被拒绝的登录不保留恢复令牌
+        assert!(!resp.resume_token.is_empty());
+        let (_peer, replay) = mock::login_with_resp(server.clone(), LoginReq { resume_token: resp.resume_token, ..Default::default() }).await;
+        assert!(replay.user_id.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use flare_core::flare_net::net::Platform;
+
+/// 多端登录策略，新连接登录时决定需要踢下线的旧连接
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LoginPolicy {
+    /// 不限制
+    #[default]
+    AllowAll,
+    /// 同一平台只保留一个连接
+    OnePerPlatform,
+    /// 移动端、桌面端各保留一个连接
+    OneMobileOneDesktop,
+    /// 最多保留 N 个连接，超出时踢掉最早登录的连接
+    MaxDevices(usize),
+}
+
+/// 是否为移动端，平板归为移动端
+pub fn is_mobile(platform: Platform) -> bool {
+    matches!(platform, Platform::Ios | Platform::Android | Platform::Apad | Platform::Ipad)
+}
+
+impl LoginPolicy {
+    /// 计算被新登录顶替的连接
+    ///
+    /// `existing` 为用户已有的连接 `(conn_id, platform)`，按登录时间从早到晚排列
+    pub fn superseded(&self, existing: &[(String, Platform)], platform: Platform) -> Vec<String> {
+        match self {
+            LoginPolicy::AllowAll => Vec::new(),
+            LoginPolicy::OnePerPlatform => existing.iter()
+                .filter(|(_, p)| *p == platform)
+                .map(|(conn_id, _)| conn_id.clone())
+                .collect(),
+            LoginPolicy::OneMobileOneDesktop => existing.iter()
+                .filter(|(_, p)| is_mobile(*p) == is_mobile(platform))
+                .map(|(conn_id, _)| conn_id.clone())
+                .collect(),
+            LoginPolicy::MaxDevices(max) => {
+                let overflow = (existing.len() + 1).saturating_sub((*max).max(1));
+                existing.iter()
+                    .take(overflow)
+                    .map(|(conn_id, _)| conn_id.clone())
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_superseded() {
+        let existing = vec![
+            ("c1".to_string(), Platform::Ios),
+            ("c2".to_string(), Platform::Windows),
+            ("c3".to_string(), Platform::Web),
+        ];
+        assert!(LoginPolicy::AllowAll.superseded(&existing, Platform::Ios).is_empty());
+        assert_eq!(LoginPolicy::OnePerPlatform.superseded(&existing, Platform::Web), ["c3"]);
+        assert_eq!(LoginPolicy::OneMobileOneDesktop.superseded(&existing, Platform::Android), ["c1"]);
+        assert_eq!(LoginPolicy::OneMobileOneDesktop.superseded(&existing, Platform::Osx), ["c2", "c3"]);
+        assert_eq!(LoginPolicy::MaxDevices(2).superseded(&existing, Platform::Linux), ["c1", "c2"]);
+        assert!(LoginPolicy::MaxDevices(4).superseded(&existing, Platform::Linux).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_kick_same_platform() {
+        use crate::connections::mock::{self, DEFAULT_USER};
+        use crate::server::server::Server;
+        use flare_core::flare_net::net::{Command, LoginReq};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let server = Arc::new(Server::default().with_login_policy(LoginPolicy::OnePerPlatform));
+        let login = |platform: Platform| LoginReq { platform: platform as i32, ..mock::login_req() };
+        let mut web = mock::login(server.clone(), login(Platform::Web)).await;
+        let _ios = mock::login(server.clone(), login(Platform::Ios)).await;
+        assert_eq!(server.get_user_connections(DEFAULT_USER).await.len(), 2);
+
+        // 同平台再次登录，旧连接被踢下线
+        let _web2 = mock::login(server.clone(), login(Platform::Web)).await;
+        let kick = web.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(kick.command, Command::KickOnline as i32);
+        assert!(mock::eventually(|| async { server.get_user_connections(DEFAULT_USER).await.len() == 2 }).await);
+        let platforms: Vec<_> = server.get_user_connections(DEFAULT_USER).await
+            .iter().map(|info| info.get_platform()).collect();
+        assert!(platforms.contains(&Platform::Web) && platforms.contains(&Platform::Ios));
+    }
+}
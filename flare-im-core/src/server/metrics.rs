@@ -0,0 +1,145 @@
+// 网关指标，开启 `metrics` feature 时注册到 Prometheus 默认注册表，否则为空操作
+#![cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+
+use flare_core::flare_net::net::{Command, Platform};
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+mod imp {
+    use flare_core::metrics::prometheus::{
+        register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, HistogramVec, IntCounterVec, IntGaugeVec,
+    };
+    use flare_core::metrics::LATENCY_BUCKETS;
+    use std::sync::LazyLock;
+
+    pub static CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        register_int_gauge_vec!("flare_im_connections", "Authenticated connections", &["protocol", "platform"]).unwrap()
+    });
+    pub static AUTH: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec!("flare_im_auth_total", "Authentication attempts", &["result"]).unwrap()
+    });
+    pub static MESSAGES_IN: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec!("flare_im_messages_in_total", "Messages received from clients", &["command"]).unwrap()
+    });
+    pub static MESSAGES_OUT: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec!("flare_im_messages_out_total", "Messages sent to clients", &["command"]).unwrap()
+    });
+    pub static SEND_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec!("flare_im_send_errors_total", "Failed sends to clients", &["command"]).unwrap()
+    });
+    pub static HANDLER_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+        register_histogram_vec!(
+            "flare_im_handler_duration_seconds",
+            "Command handler latency",
+            &["command"],
+            LATENCY_BUCKETS.to_vec()
+        ).unwrap()
+    });
+    pub static HEARTBEAT_TIMEOUTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec!("flare_im_heartbeat_timeouts_total", "Connections closed by heartbeat timeout", &["platform"]).unwrap()
+    });
+}
+
+#[cfg(feature = "metrics")]
+fn command_name(command: i32) -> &'static str {
+    Command::try_from(command).map(|c| c.as_str_name()).unwrap_or("UNKNOWN")
+}
+
+/// 已认证的连接登记到连接表
+pub(crate) fn connection_opened(protocol: &str, platform: Platform) {
+    #[cfg(feature = "metrics")]
+    imp::CONNECTIONS.with_label_values(&[protocol, platform.as_str_name()]).inc();
+}
+
+/// 已认证的连接从连接表移除
+pub(crate) fn connection_closed(protocol: &str, platform: Platform) {
+    #[cfg(feature = "metrics")]
+    imp::CONNECTIONS.with_label_values(&[protocol, platform.as_str_name()]).dec();
+}
+
+pub(crate) fn auth(success: bool) {
+    #[cfg(feature = "metrics")]
+    imp::AUTH.with_label_values(&[if success { "success" } else { "failure" }]).inc();
+}
+
+pub(crate) fn message_in(command: i32) {
+    #[cfg(feature = "metrics")]
+    imp::MESSAGES_IN.with_label_values(&[command_name(command)]).inc();
+}
+
+/// 记录发送结果
+pub(crate) fn message_out(command: i32, ok: bool) {
+    #[cfg(feature = "metrics")]
+    if ok {
+        imp::MESSAGES_OUT.with_label_values(&[command_name(command)]).inc();
+    } else {
+        imp::SEND_ERRORS.with_label_values(&[command_name(command)]).inc();
+    }
+}
+
+pub(crate) fn handler_latency(command: Command, elapsed: Duration) {
+    #[cfg(feature = "metrics")]
+    imp::HANDLER_LATENCY.with_label_values(&[command.as_str_name()]).observe(elapsed.as_secs_f64());
+}
+
+pub(crate) fn heartbeat_timeout(platform: Platform) {
+    #[cfg(feature = "metrics")]
+    imp::HEARTBEAT_TIMEOUTS.with_label_values(&[platform.as_str_name()]).inc();
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::connections::mock;
+    use crate::server::auth_handler::{AuthCommandHandler, DefAuthHandler};
+    use crate::server::handlers::ServerMessageHandler;
+    use crate::server::server::Server;
+    use crate::server::server_handler::{DefServerHandler, ServerCommandHandler};
+    use crate::server::sys_handler::SystemCommandHandler;
+    use flare_core::flare_net::net::{LoginReq, Message as ProtoMessage};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_gateway_metrics() {
+        let connections = || imp::CONNECTIONS.with_label_values(&["mock", "MINI_WEB"]).get();
+        let before = connections();
+        let auth_failures = imp::AUTH.with_label_values(&["failure"]).get();
+
+        let server = Arc::new(Server::default());
+        let mut peer = mock::login(server.clone(), LoginReq { platform: Platform::MiniWeb as i32, ..mock::login_req() }).await;
+        assert_eq!(connections(), before + 1);
+
+        // 心跳计入收到的消息
+        let pings = imp::MESSAGES_IN.with_label_values(&["PING"]).get();
+        let pongs = imp::MESSAGES_OUT.with_label_values(&["PONG"]).get();
+        peer.tx.send(ProtoMessage { command: Command::Ping as i32, ..Default::default() }).unwrap();
+        peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert!(imp::MESSAGES_IN.with_label_values(&["PING"]).get() > pings);
+        assert!(imp::MESSAGES_OUT.with_label_values(&["PONG"]).get() > pongs);
+
+        // 登录令牌为空时认证失败
+        let (conn, peer_fail) = mock::pair();
+        peer_fail.tx.send(ProtoMessage { command: Command::Login as i32, ..Default::default() }).unwrap();
+        server.add_connection(Box::new(conn)).await;
+        assert!(imp::AUTH.with_label_values(&["failure"]).get() > auth_failures);
+
+        drop(peer);
+        assert!(mock::eventually(|| async { connections() == before }).await);
+        assert!(flare_core::metrics::encode().contains("flare_im_connections"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_connection_not_counted() {
+        let connections = || imp::CONNECTIONS.with_label_values(&["mock", "OSX"]).get();
+        let before = connections();
+        let server = Arc::new(Server::new(ServerMessageHandler::new(
+            AuthCommandHandler::new(DefAuthHandler::new()),
+            ServerCommandHandler::new(DefServerHandler::new()),
+            SystemCommandHandler::new(mock::RejectNew),
+        )));
+        let _peer = mock::login(server.clone(), LoginReq { platform: Platform::Osx as i32, ..mock::login_req() }).await;
+        assert!(mock::eventually(|| async { server.get_connections().await.is_empty() }).await);
+        assert_eq!(connections(), before);
+    }
+}
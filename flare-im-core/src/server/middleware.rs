@@ -0,0 +1,132 @@
+use crate::server::handlers::CommandHandler;
+use async_trait::async_trait;
+use flare_core::context::AppContext;
+use flare_core::error::Result;
+use flare_core::flare_net::net::Response;
+use log::{debug, warn};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 命令处理中间件
+///
+/// 按注册顺序包裹命令处理器，`next.run(ctx)` 之前的代码为前置处理，之后的为后置处理，
+/// 不调用 `next` 即可直接返回响应。
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    async fn handle(&self, ctx: &AppContext, next: Next<'_>) -> Result<Response>;
+}
+
+/// 中间件链中剩余的部分
+pub struct Next<'a> {
+    middlewares: &'a [Arc<dyn Middleware>],
+    handler: &'a dyn CommandHandler,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(middlewares: &'a [Arc<dyn Middleware>], handler: &'a dyn CommandHandler) -> Self {
+        Self { middlewares, handler }
+    }
+
+    /// 调用下一个中间件，全部调用完后交给命令处理器
+    pub async fn run(self, ctx: &AppContext) -> Result<Response> {
+        match self.middlewares.split_first() {
+            Some((first, rest)) => first.handle(ctx, Next::new(rest, self.handler)).await,
+            None => self.handler.handle_command(ctx).await,
+        }
+    }
+}
+
+/// 记录命令处理耗时和结果
+#[derive(Debug, Default)]
+pub struct LogMiddleware;
+
+#[async_trait]
+impl Middleware for LogMiddleware {
+    async fn handle(&self, ctx: &AppContext, next: Next<'_>) -> Result<Response> {
+        let start = Instant::now();
+        let result = next.run(ctx).await;
+        match &result {
+            Ok(resp) => debug!("{:?} from {} handled in {:?}, code {}",
+                ctx.command(), ctx.conn_id(), start.elapsed(), resp.code),
+            Err(e) => warn!("{:?} from {} failed in {:?}: {}",
+                ctx.command(), ctx.conn_id(), start.elapsed(), e),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::handlers::ServerMessageHandler;
+    use crate::server::auth_handler::DefAuthHandler;
+    use crate::server::server_handler::DefServerHandler;
+    use crate::server::sys_handler::DefSystemHandler;
+    use flare_core::context::AppContextBuilder;
+    use flare_core::flare_net::net::{Command, ResCode};
+    use std::sync::Mutex;
+
+    struct Record(&'static str, Arc<Mutex<Vec<String>>>);
+
+    #[async_trait]
+    impl Middleware for Record {
+        async fn handle(&self, ctx: &AppContext, next: Next<'_>) -> Result<Response> {
+            self.1.lock().unwrap().push(format!("before {}", self.0));
+            let resp = next.run(ctx).await?;
+            self.1.lock().unwrap().push(format!("after {} {}", self.0, resp.code));
+            Ok(resp)
+        }
+    }
+
+    /// 拒绝没有 user_id 的请求
+    struct RequireUser;
+
+    #[async_trait]
+    impl Middleware for RequireUser {
+        async fn handle(&self, ctx: &AppContext, next: Next<'_>) -> Result<Response> {
+            if ctx.user_id().unwrap_or_default().is_empty() {
+                return Ok(Response {
+                    code: ResCode::Unauthorized as i32,
+                    message: "login required".into(),
+                    data: Vec::new(),
+                });
+            }
+            next.run(ctx).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_chain() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let handler = ServerMessageHandler::<DefServerHandler, DefAuthHandler, DefSystemHandler>::default()
+            .with_middleware(Record("outer", events.clone()))
+            .with_middleware(RequireUser)
+            .with_middleware(Record("inner", events.clone()));
+
+        let ctx = AppContextBuilder::new()
+            .remote_addr("127.0.0.1".into())
+            .command(Some(Command::ClientSendMessage))
+            .data(b"hi".to_vec())
+            .build()
+            .unwrap();
+        let resp = handler.handle_command(&ctx).await.unwrap();
+        assert_eq!(resp.code, ResCode::Unauthorized as i32);
+        assert_eq!(*events.lock().unwrap(), ["before outer", "after outer 16"]);
+
+        events.lock().unwrap().clear();
+        let ctx = AppContextBuilder::new()
+            .remote_addr("127.0.0.1".into())
+            .user_id("u1".into())
+            .command(Some(Command::ClientSendMessage))
+            .data(b"hi".to_vec())
+            .build()
+            .unwrap();
+        let resp = handler.handle_command(&ctx).await.unwrap();
+        assert_eq!(*events.lock().unwrap(), [
+            "before outer".to_string(),
+            "before inner".to_string(),
+            format!("after inner {}", resp.code),
+            format!("after outer {}", resp.code),
+        ]);
+    }
+}
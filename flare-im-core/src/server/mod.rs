@@ -3,3 +3,21 @@ pub mod handlers;
 pub mod auth_handler;
 pub mod sys_handler;
 pub mod server_handler;
+pub mod session_store;
+pub mod delivery;
+pub mod offline;
+pub mod room;
+pub mod presence;
+pub mod login_policy;
+pub mod lifecycle;
+pub mod heartbeat;
+pub mod middleware;
+pub mod router;
+pub mod shutdown;
+pub mod rate_limit;
+pub(crate) mod metrics;
+pub mod resume;
+#[cfg(feature = "cluster")]
+pub mod rpc_router;
+#[cfg(feature = "admin")]
+pub mod admin;
@@ -0,0 +1,365 @@
+use async_trait::async_trait;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::{Message as ProtoMessage, PullResp};
+use prost::Message as ProstMessage;
+use std::collections::{HashMap, VecDeque};
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// 离线消息默认每页数量
+pub const DEFAULT_PULL_LIMIT: usize = 100;
+/// 离线消息每页最大数量，避免单页超过帧长度上限
+pub const MAX_PULL_LIMIT: usize = 500;
+
+/// 离线消息存储
+///
+/// 每个用户的离线消息按写入顺序编号，游标即下一条待读取消息的编号。
+/// 客户端带着游标拉取时，游标之前的消息视为已确认，存储可以删除它们。
+#[async_trait]
+pub trait OfflineStore: Send + Sync {
+    /// 保存一条离线消息
+    async fn append(&self, user_id: &str, msg: ProtoMessage) -> Result<()>;
+    /// 从游标处读取一页消息
+    async fn fetch(&self, user_id: &str, cursor: u64, limit: usize) -> Result<PullResp>;
+}
+
+/// 从 `base` 开始编号的消息列表中读取一页
+fn page(base: u64, msgs: &[ProtoMessage], cursor: u64, limit: usize) -> PullResp {
+    let end = base + msgs.len() as u64;
+    let start = cursor.clamp(base, end);
+    let messages: Vec<_> = msgs.iter()
+        .skip((start - base) as usize)
+        .take(limit)
+        .cloned()
+        .collect();
+    let next_cursor = start + messages.len() as u64;
+    PullResp {
+        messages,
+        next_cursor,
+        has_more: next_cursor < end,
+    }
+}
+
+#[derive(Default)]
+struct Mailbox {
+    base: u64,
+    msgs: VecDeque<ProtoMessage>,
+}
+
+/// 基于内存的离线消息存储
+#[derive(Clone, Default)]
+pub struct MemoryOfflineStore {
+    mailboxes: Arc<Mutex<HashMap<String, Mailbox>>>,
+}
+
+impl MemoryOfflineStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl OfflineStore for MemoryOfflineStore {
+    async fn append(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        self.mailboxes.lock().await
+            .entry(user_id.to_string())
+            .or_default()
+            .msgs
+            .push_back(msg);
+        Ok(())
+    }
+
+    async fn fetch(&self, user_id: &str, cursor: u64, limit: usize) -> Result<PullResp> {
+        let mut mailboxes = self.mailboxes.lock().await;
+        let Some(mailbox) = mailboxes.get_mut(user_id) else {
+            return Ok(PullResp { next_cursor: cursor, ..Default::default() });
+        };
+        // 删除已确认的消息
+        while mailbox.base < cursor && mailbox.msgs.pop_front().is_some() {
+            mailbox.base += 1;
+        }
+        Ok(page(mailbox.base, mailbox.msgs.make_contiguous(), cursor, limit))
+    }
+}
+
+/// 基于追加写文件的离线消息存储
+///
+/// 每个用户一个 `.meta` 文件记录日志首条消息的编号、已确认的游标及其在日志中的偏移，
+/// 消息追加到以首条编号命名的 `.log` 文件。消息全部确认后先写入新的编号再删除旧日志，
+/// 中途崩溃只会残留不再读取的旧文件，新消息的编号不会落到已确认的游标之前。
+#[derive(Clone)]
+pub struct FileOfflineStore {
+    dir: PathBuf,
+    // 每个用户一把锁，不同用户的读写互不阻塞
+    locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Meta {
+    // 日志首条消息的编号
+    base: u64,
+    acked: u64,
+    // 编号为 acked 的消息在日志中的字节偏移
+    offset: u64,
+}
+
+fn io_error(action: &str, e: std::io::Error) -> FlareErr {
+    FlareErr::Error(format!("failed to {} offline log: {}", action, e))
+}
+
+/// 读取一条长度前缀记录，返回内容和占用的字节数
+///
+/// 文件结束或末尾记录不完整（追加写入时崩溃）时返回 None
+async fn read_record<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<(Vec<u8>, u64)>> {
+    let mut len = 0u64;
+    let mut header = 0u64;
+    loop {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(io_error("read", e)),
+        };
+        len |= ((byte & 0x7f) as u64) << (7 * header);
+        header += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if header == 10 {
+            return Err(FlareErr::Error("invalid offline record length".to_string()));
+        }
+    }
+    let mut buf = Vec::new();
+    reader.take(len).read_to_end(&mut buf).await.map_err(|e| io_error("read", e))?;
+    if (buf.len() as u64) < len {
+        return Ok(None);
+    }
+    Ok(Some((buf, header + len)))
+}
+
+impl FileOfflineStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| FlareErr::Error(format!("failed to create offline dir {}: {}", dir.display(), e)))?;
+        Ok(Self {
+            dir,
+            locks: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    fn path(&self, user_id: &str, ext: &str) -> PathBuf {
+        // user_id 转为十六进制，避免出现路径字符
+        let name: String = user_id.bytes().map(|b| format!("{:02x}", b)).collect();
+        self.dir.join(format!("{}.{}", name, ext))
+    }
+
+    fn log_path(&self, user_id: &str, base: u64) -> PathBuf {
+        self.path(user_id, &format!("{}.log", base))
+    }
+
+    async fn lock_user(&self, user_id: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().await;
+            // 清理没有读写在进行的用户
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks.entry(user_id.to_string()).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+
+    async fn read_meta(&self, user_id: &str) -> Result<Meta> {
+        match tokio::fs::read_to_string(self.path(user_id, "meta")).await {
+            Ok(text) => {
+                let mut parts = text.split_whitespace().map(|s| s.parse::<u64>().unwrap_or(0));
+                Ok(Meta {
+                    base: parts.next().unwrap_or(0),
+                    acked: parts.next().unwrap_or(0),
+                    offset: parts.next().unwrap_or(0),
+                })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Meta::default()),
+            Err(e) => Err(FlareErr::Error(format!("failed to read offline meta: {}", e))),
+        }
+    }
+
+    /// 先写临时文件再重命名，崩溃时不会留下写了一半的元数据
+    async fn write_meta(&self, user_id: &str, meta: Meta) -> Result<()> {
+        let tmp = self.path(user_id, "meta.tmp");
+        tokio::fs::write(&tmp, format!("{} {} {}", meta.base, meta.acked, meta.offset)).await
+            .map_err(|e| FlareErr::Error(format!("failed to write offline meta: {}", e)))?;
+        tokio::fs::rename(&tmp, self.path(user_id, "meta")).await
+            .map_err(|e| FlareErr::Error(format!("failed to write offline meta: {}", e)))
+    }
+}
+
+#[async_trait]
+impl OfflineStore for FileOfflineStore {
+    async fn append(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        let _guard = self.lock_user(user_id).await;
+        let meta = self.read_meta(user_id).await?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path(user_id, meta.base))
+            .await
+            .map_err(|e| io_error("open", e))?;
+        file.write_all(&msg.encode_length_delimited_to_vec()).await
+            .map_err(|e| io_error("append", e))?;
+        // tokio 的文件写入在后台完成，返回前等待写入结束
+        file.flush().await.map_err(|e| io_error("append", e))
+    }
+
+    async fn fetch(&self, user_id: &str, cursor: u64, limit: usize) -> Result<PullResp> {
+        let _guard = self.lock_user(user_id).await;
+        let meta = self.read_meta(user_id).await?;
+        let log_path = self.log_path(user_id, meta.base);
+        let file = match tokio::fs::File::open(&log_path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(PullResp { next_cursor: meta.acked, ..Default::default() });
+            }
+            Err(e) => return Err(io_error("open", e)),
+        };
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(meta.offset)).await.map_err(|e| io_error("seek", e))?;
+
+        // 跳过客户端已确认的消息，只读取本页和下一条
+        let mut next = meta;
+        let mut record = read_record(&mut reader).await?;
+        while next.acked < cursor {
+            let Some((_, len)) = record else { break };
+            next.acked += 1;
+            next.offset += len;
+            record = read_record(&mut reader).await?;
+        }
+        let mut messages = Vec::new();
+        while messages.len() < limit {
+            let Some((data, _)) = record else { break };
+            messages.push(ProtoMessage::decode(&data[..])?);
+            record = read_record(&mut reader).await?;
+        }
+        let has_more = record.is_some();
+
+        if messages.is_empty() && !has_more {
+            if next.acked != meta.base {
+                // 全部确认，新消息写入以下一个编号命名的日志
+                self.write_meta(user_id, Meta { base: next.acked, acked: next.acked, offset: 0 }).await?;
+                tokio::fs::remove_file(&log_path).await.map_err(|e| io_error("remove", e))?;
+            }
+        } else if next != meta {
+            self.write_meta(user_id, next).await?;
+        }
+        Ok(PullResp {
+            next_cursor: next.acked + messages.len() as u64,
+            messages,
+            has_more,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(data: &str) -> ProtoMessage {
+        ProtoMessage {
+            data: data.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn data(resp: &PullResp) -> Vec<String> {
+        resp.messages.iter().map(|m| String::from_utf8(m.data.clone()).unwrap()).collect()
+    }
+
+    async fn check(store: impl OfflineStore) {
+        for s in ["a", "b", "c"] {
+            store.append("u1", msg(s)).await.unwrap();
+        }
+        let first = store.fetch("u1", 0, 2).await.unwrap();
+        assert_eq!(data(&first), ["a", "b"]);
+        assert!(first.has_more);
+
+        let second = store.fetch("u1", first.next_cursor, 2).await.unwrap();
+        assert_eq!(data(&second), ["c"]);
+        assert!(!second.has_more);
+
+        // 确认全部消息后，新消息的游标继续递增
+        let empty = store.fetch("u1", second.next_cursor, 2).await.unwrap();
+        assert!(empty.messages.is_empty());
+        store.append("u1", msg("d")).await.unwrap();
+        let stale = store.fetch("u1", 0, 10).await.unwrap();
+        assert_eq!(data(&stale), ["d"]);
+        assert_eq!(stale.next_cursor, 4);
+
+        assert!(store.fetch("u2", 0, 10).await.unwrap().messages.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_offline_store() {
+        check(MemoryOfflineStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_pull_after_login() {
+        use crate::connections::mock::{self, DEFAULT_USER};
+        use crate::server::server::Server;
+        use flare_core::flare_net::net::{Command, PullReq, ResCode, Response};
+        use std::time::Duration;
+
+        let store = MemoryOfflineStore::new();
+        let server = Arc::new(Server::default().with_offline_store(store.clone()));
+        server.send_to_user(DEFAULT_USER, msg("hello")).await.unwrap();
+
+        let mut peer = mock::login_default(server).await;
+        peer.tx.send(ProtoMessage {
+            command: Command::ClientPullMessage as i32,
+            data: PullReq::default().encode_to_vec(),
+            client_id: "pull-1".into(),
+            ..Default::default()
+        }).unwrap();
+
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(reply.client_id, "pull-1");
+        let resp = Response::decode(&reply.data[..]).unwrap();
+        assert_eq!(resp.code, ResCode::Success as i32);
+        let page = PullResp::decode(&resp.data[..]).unwrap();
+        assert_eq!(data(&page), ["hello"]);
+        assert_eq!(page.next_cursor, 1);
+
+        // 每页数量不超过上限
+        for _ in 0..=MAX_PULL_LIMIT {
+            store.append(DEFAULT_USER, msg("x")).await.unwrap();
+        }
+        peer.tx.send(ProtoMessage {
+            command: Command::ClientPullMessage as i32,
+            data: PullReq { cursor: page.next_cursor, limit: u32::MAX, ..Default::default() }.encode_to_vec(),
+            client_id: "pull-2".into(),
+            ..Default::default()
+        }).unwrap();
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        let resp = Response::decode(&reply.data[..]).unwrap();
+        let page = PullResp::decode(&resp.data[..]).unwrap();
+        assert_eq!(page.messages.len(), MAX_PULL_LIMIT);
+        assert!(page.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_file_offline_store() {
+        let dir = std::env::temp_dir().join(format!("flare-offline-{}", uuid::Uuid::new_v4()));
+        let store = FileOfflineStore::new(&dir).unwrap();
+        check(store.clone()).await;
+
+        // 追加写入中断留下的不完整记录不影响读取
+        store.append("u3", msg("a")).await.unwrap();
+        let mut file = std::fs::OpenOptions::new().append(true).open(store.log_path("u3", 0)).unwrap();
+        std::io::Write::write_all(&mut file, &[10, b'x']).unwrap();
+        let resp = store.fetch("u3", 0, 10).await.unwrap();
+        assert_eq!(data(&resp), ["a"]);
+        assert!(!resp.has_more);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use crate::server::server::ConnectionInfo;
+use flare_core::flare_net::net::{Command, Message as ProtoMessage, Presence, PresenceState};
+use log::{debug, warn};
+use prost::Message;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Default)]
+struct PresenceTable {
+    states: HashMap<String, Presence>,                  // user_id -> 最近一次的状态
+    subscribers: HashMap<String, HashSet<String>>,      // 被订阅者 -> 订阅者
+    subscriptions: HashMap<String, HashSet<String>>,    // 订阅者 -> 被订阅者
+}
+
+/// 在线状态
+///
+/// 由用户在本节点上的所有连接汇总：有前台连接为在线，全部在后台为后台，没有连接为离线。
+/// 状态变化时以 `SERVER_PUSH_NOTICE` 推送给订阅者。
+#[derive(Clone)]
+pub struct PresenceHub {
+    table: Arc<Mutex<PresenceTable>>,
+    connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>,
+    user_connections: Arc<Mutex<HashMap<String, Vec<String>>>>,
+}
+
+impl PresenceHub {
+    pub(crate) fn new(
+        connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>,
+        user_connections: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    ) -> Self {
+        Self {
+            table: Arc::new(Mutex::new(PresenceTable::default())),
+            connections,
+            user_connections,
+        }
+    }
+
+    /// 用户在本节点的连接
+    async fn user_conns(&self, user_id: &str) -> Vec<ConnectionInfo> {
+        let conn_ids = self.user_connections.lock().await.get(user_id).cloned().unwrap_or_default();
+        let conns = self.connections.lock().await;
+        conn_ids.iter().filter_map(|id| conns.get(id).cloned()).collect()
+    }
+
+    /// 根据当前连接计算在线状态
+    async fn compute(&self, user_id: &str, last_seen: i64) -> Presence {
+        let conns = self.user_conns(user_id).await;
+        let mut presence = Presence {
+            user_id: user_id.to_string(),
+            last_seen,
+            ..Default::default()
+        };
+        if conns.is_empty() {
+            presence.set_state(PresenceState::Offline);
+            return presence;
+        }
+        let mut background = true;
+        for info in &conns {
+            background &= info.is_background().await;
+            let platform = info.get_platform() as i32;
+            if !presence.platforms.contains(&platform) {
+                presence.platforms.push(platform);
+            }
+        }
+        presence.platforms.sort();
+        presence.set_state(if background { PresenceState::Background } else { PresenceState::Online });
+        presence.last_seen = chrono::Utc::now().timestamp_millis();
+        presence
+    }
+
+    /// 查询用户的在线状态
+    pub async fn get(&self, user_id: &str) -> Presence {
+        let last_seen = self.table.lock().await.states.get(user_id).map(|p| p.last_seen).unwrap_or(0);
+        self.compute(user_id, last_seen).await
+    }
+
+    /// 连接或后台状态变化后重新计算，状态变化时通知订阅者
+    pub async fn refresh(&self, user_id: &str) {
+        let last_seen = self.table.lock().await.states.get(user_id).map(|p| p.last_seen).unwrap_or(0);
+        let mut presence = self.compute(user_id, last_seen).await;
+        let subscribers = {
+            let mut table = self.table.lock().await;
+            let previous = table.states.get(user_id);
+            if previous.map(|p| (p.state, &p.platforms)) == Some((presence.state, &presence.platforms)) {
+                return;
+            }
+            if presence.state() == PresenceState::Offline {
+                // 离线时记录最后在线时间，并清理该用户的订阅
+                presence.last_seen = chrono::Utc::now().timestamp_millis();
+                if let Some(targets) = table.subscriptions.remove(user_id) {
+                    for target in targets {
+                        Self::remove_subscriber(&mut table, &target, user_id);
+                    }
+                }
+            }
+            table.states.insert(user_id.to_string(), presence.clone());
+            table.subscribers.get(user_id).cloned().unwrap_or_default()
+        };
+        debug!("Presence of {} changed to {:?}", user_id, presence.state());
+        self.notify(subscribers, presence).await;
+    }
+
+    async fn notify(&self, subscribers: HashSet<String>, presence: Presence) {
+        let msg = ProtoMessage {
+            command: Command::ServerPushNotice as i32,
+            data: presence.encode_to_vec(),
+            ..Default::default()
+        };
+        for subscriber in subscribers {
+            for info in self.user_conns(&subscriber).await {
+                if let Err(e) = info.push(msg.clone()).await {
+                    warn!("Failed to push presence to {}: {}", info.get_conn_id(), e);
+                }
+            }
+        }
+    }
+
+    /// 订阅用户的在线状态，返回订阅用户的当前状态
+    pub async fn subscribe(&self, subscriber: &str, user_ids: &[String]) -> Vec<Presence> {
+        {
+            let mut table = self.table.lock().await;
+            for user_id in user_ids {
+                table.subscribers.entry(user_id.clone()).or_default().insert(subscriber.to_string());
+                table.subscriptions.entry(subscriber.to_string()).or_default().insert(user_id.clone());
+            }
+        }
+        let mut presences = Vec::with_capacity(user_ids.len());
+        for user_id in user_ids {
+            presences.push(self.get(user_id).await);
+        }
+        presences
+    }
+
+    /// 用户订阅的在线状态
+    pub async fn subscriptions_of(&self, subscriber: &str) -> Vec<String> {
+        let table = self.table.lock().await;
+        table.subscriptions.get(subscriber)
+            .map(|targets| targets.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 取消订阅
+    pub async fn unsubscribe(&self, subscriber: &str, user_ids: &[String]) {
+        let mut table = self.table.lock().await;
+        for user_id in user_ids {
+            Self::remove_subscriber(&mut table, user_id, subscriber);
+            if let Some(targets) = table.subscriptions.get_mut(subscriber) {
+                targets.remove(user_id);
+                if targets.is_empty() {
+                    table.subscriptions.remove(subscriber);
+                }
+            }
+        }
+    }
+
+    fn remove_subscriber(table: &mut PresenceTable, target: &str, subscriber: &str) {
+        if let Some(subscribers) = table.subscribers.get_mut(target) {
+            subscribers.remove(subscriber);
+            if subscribers.is_empty() {
+                table.subscribers.remove(target);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock;
+    use crate::server::auth_handler::{AuthCommandHandler, AuthHandler};
+    use crate::server::handlers::ServerMessageHandler;
+    use crate::server::server::Server;
+    use crate::server::server_handler::{DefServerHandler, ServerCommandHandler};
+    use crate::server::sys_handler::{DefSystemHandler, SystemCommandHandler};
+    use async_trait::async_trait;
+    use flare_core::context::AppContext;
+    use flare_core::error::Result;
+    use flare_core::flare_net::net::{LoginReq, LoginResp, PresenceList, PresenceSubReq, ResCode, Response};
+    use std::time::Duration;
+
+    /// 直接使用请求中的 user_id 登录
+    struct UserAuth;
+
+    #[async_trait]
+    impl AuthHandler for UserAuth {
+        async fn handle_login(&self, ctx: &AppContext) -> Result<Response> {
+            let req = ctx.get_data_as::<LoginReq>()?;
+            Ok(Response {
+                code: ResCode::Success as i32,
+                message: String::new(),
+                data: LoginResp { user_id: req.user_id, ..Default::default() }.encode_to_vec(),
+            })
+        }
+
+        async fn handle_logout(&self, _ctx: &AppContext) -> Result<Response> {
+            Ok(Response::default())
+        }
+    }
+
+    fn login(user_id: &str) -> LoginReq {
+        LoginReq { user_id: user_id.into(), ..Default::default() }
+    }
+
+    async fn notice(peer: &mut mock::MockPeer) -> Presence {
+        let msg = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(msg.command, Command::ServerPushNotice as i32);
+        Presence::decode(&msg.data[..]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_presence_subscription() {
+        let server = Arc::new(Server::new(ServerMessageHandler::new(
+            AuthCommandHandler::new(UserAuth),
+            ServerCommandHandler::new(DefServerHandler::new()),
+            SystemCommandHandler::new(DefSystemHandler::new()),
+        )));
+        let mut alice = mock::login(server.clone(), login("alice")).await;
+        let bob = mock::login(server.clone(), login("bob")).await;
+
+        alice.tx.send(ProtoMessage {
+            command: Command::SubscribePresence as i32,
+            data: PresenceSubReq { user_ids: vec!["bob".into()], ..Default::default() }.encode_to_vec(),
+            ..Default::default()
+        }).unwrap();
+        let reply = alice.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        let list = PresenceList::decode(&Response::decode(&reply.data[..]).unwrap().data[..]).unwrap();
+        assert_eq!(list.presences[0].state(), PresenceState::Online);
+
+        // bob 切到后台
+        bob.tx.send(ProtoMessage {
+            command: Command::SetBackground as i32,
+            data: vec![1],
+            ..Default::default()
+        }).unwrap();
+        let presence = notice(&mut alice).await;
+        assert_eq!((presence.user_id.as_str(), presence.state()), ("bob", PresenceState::Background));
+
+        // bob 断开连接
+        drop(bob);
+        let presence = notice(&mut alice).await;
+        assert_eq!(presence.state(), PresenceState::Offline);
+        assert!(presence.last_seen > 0);
+        assert_eq!(server.get_presence("bob").await.last_seen, presence.last_seen);
+    }
+}
@@ -0,0 +1,262 @@
+use flare_core::flare_net::net::Command;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// 令牌桶速率，`burst` 为桶容量，`per_second` 为每秒补充的令牌数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+impl Rate {
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        Self { burst, per_second }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: &Rate, now: Instant) -> Self {
+        Self {
+            tokens: rate.burst as f64,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, rate: &Rate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate.per_second).min(rate.burst as f64);
+        self.last = now;
+    }
+}
+
+/// 限流配置，未设置的维度不限流
+///
+/// 按连接、按用户（本节点所有连接共享）和按命令（每个连接单独计数）三个维度，
+/// 请求需要在所有维度上都有令牌才会放行。心跳不经过限流，
+/// ACK 总会确认可靠推送，被限流时只是不再交给业务处理器。
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+    pub per_connection: Option<Rate>,
+    pub per_user: Option<Rate>,
+    pub per_command: HashMap<Command, Rate>,
+    /// 在窗口内被限流达到次数后断开连接
+    pub disconnect_after: Option<(u32, Duration)>,
+}
+
+impl RateLimitConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_connection_limit(mut self, rate: Rate) -> Self {
+        self.per_connection = Some(rate);
+        self
+    }
+
+    pub fn with_user_limit(mut self, rate: Rate) -> Self {
+        self.per_user = Some(rate);
+        self
+    }
+
+    pub fn with_command_limit(mut self, command: Command, rate: Rate) -> Self {
+        self.per_command.insert(command, rate);
+        self
+    }
+
+    pub fn with_disconnect_after(mut self, violations: u32, window: Duration) -> Self {
+        self.disconnect_after = Some((violations.max(1), window));
+        self
+    }
+}
+
+/// 限流结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allow,
+    Limited,
+    /// 被限流且超过断开阈值
+    Disconnect,
+}
+
+#[derive(Debug)]
+struct ConnState {
+    bucket: Option<TokenBucket>,
+    commands: HashMap<Command, TokenBucket>,
+    violations: u32,
+    window_start: Instant,
+}
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    connections: HashMap<String, ConnState>,
+    users: HashMap<String, TokenBucket>,
+}
+
+/// 令牌桶限流器
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: Arc<RateLimitConfig>,
+    state: Arc<Mutex<LimiterState>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(LimiterState::default())),
+        }
+    }
+
+    /// 检查一次请求，放行时扣除各维度的令牌
+    pub async fn check(&self, conn_id: &str, user_id: &str, command: Command) -> RateDecision {
+        let now = Instant::now();
+        let config = &self.config;
+        let mut state = self.state.lock().await;
+        let LimiterState { connections, users } = &mut *state;
+
+        let conn = connections.entry(conn_id.to_string()).or_insert_with(|| ConnState {
+            bucket: config.per_connection.as_ref().map(|rate| TokenBucket::new(rate, now)),
+            commands: HashMap::new(),
+            violations: 0,
+            window_start: now,
+        });
+        let mut buckets: Vec<(&mut TokenBucket, &Rate)> = Vec::with_capacity(3);
+        if let (Some(bucket), Some(rate)) = (conn.bucket.as_mut(), config.per_connection.as_ref()) {
+            buckets.push((bucket, rate));
+        }
+        if let Some(rate) = config.per_command.get(&command) {
+            let bucket = conn.commands.entry(command).or_insert_with(|| TokenBucket::new(rate, now));
+            buckets.push((bucket, rate));
+        }
+        if let Some(rate) = config.per_user.as_ref() {
+            let bucket = users.entry(user_id.to_string()).or_insert_with(|| TokenBucket::new(rate, now));
+            buckets.push((bucket, rate));
+        }
+
+        for (bucket, rate) in buckets.iter_mut() {
+            bucket.refill(rate, now);
+        }
+        if buckets.iter().all(|(bucket, _)| bucket.tokens >= 1.0) {
+            for (bucket, _) in buckets.iter_mut() {
+                bucket.tokens -= 1.0;
+            }
+            return RateDecision::Allow;
+        }
+
+        match config.disconnect_after {
+            Some((max, window)) => {
+                if now.saturating_duration_since(conn.window_start) > window {
+                    conn.window_start = now;
+                    conn.violations = 0;
+                }
+                conn.violations += 1;
+                if conn.violations >= max {
+                    RateDecision::Disconnect
+                } else {
+                    RateDecision::Limited
+                }
+            }
+            None => RateDecision::Limited,
+        }
+    }
+
+    /// 连接断开时清理状态，用户没有其它连接时一并清理用户的令牌桶
+    pub async fn remove(&self, conn_id: &str, user_id: &str, user_offline: bool) {
+        let mut state = self.state.lock().await;
+        state.connections.remove(conn_id);
+        if user_offline {
+            state.users.remove(user_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_bucket_limits() {
+        let limiter = RateLimiter::new(RateLimitConfig::new()
+            .with_connection_limit(Rate::new(3, 0.0))
+            .with_user_limit(Rate::new(4, 0.0))
+            .with_command_limit(Command::ClientSendMessage, Rate::new(1, 0.0)));
+
+        assert_eq!(limiter.check("c1", "u1", Command::ClientSendMessage).await, RateDecision::Allow);
+        assert_eq!(limiter.check("c1", "u1", Command::ClientSendMessage).await, RateDecision::Limited);
+        // 被拒绝的请求不消耗其它维度的令牌
+        assert_eq!(limiter.check("c1", "u1", Command::ClientAck).await, RateDecision::Allow);
+        assert_eq!(limiter.check("c1", "u1", Command::ClientAck).await, RateDecision::Allow);
+        assert_eq!(limiter.check("c1", "u1", Command::ClientAck).await, RateDecision::Limited);
+        // 用户维度跨连接共享
+        assert_eq!(limiter.check("c2", "u1", Command::ClientAck).await, RateDecision::Allow);
+        assert_eq!(limiter.check("c2", "u1", Command::ClientAck).await, RateDecision::Limited);
+
+        limiter.remove("c2", "u1", true).await;
+        assert_eq!(limiter.check("c2", "u1", Command::ClientAck).await, RateDecision::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_abusive_connection() {
+        use crate::connections::mock::{self, DEFAULT_USER};
+        use crate::server::server::Server;
+        use flare_core::flare_net::net::{Message as ProtoMessage, ResCode, Response};
+        use prost::Message;
+
+        let server = Arc::new(Server::default().with_rate_limit(RateLimitConfig::new()
+            .with_connection_limit(Rate::new(1, 0.0))
+            .with_disconnect_after(2, Duration::from_secs(60))));
+        let mut peer = mock::login_default(server.clone()).await;
+        for i in 0..3 {
+            peer.tx.send(ProtoMessage {
+                command: Command::ClientSendMessage as i32,
+                data: b"hi".to_vec(),
+                client_id: format!("m{}", i),
+                ..Default::default()
+            }).unwrap();
+        }
+
+        let first = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_ne!(Response::decode(&first.data[..]).unwrap().code, ResCode::RateLimited as i32);
+        let second = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(second.client_id, "m1");
+        assert_eq!(Response::decode(&second.data[..]).unwrap().code, ResCode::RateLimited as i32);
+        let third = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(Response::decode(&third.data[..]).unwrap().code, ResCode::RateLimited as i32);
+
+        assert!(mock::eventually(|| async { server.get_user_connections(DEFAULT_USER).await.is_empty() }).await);
+    }
+
+    #[tokio::test]
+    async fn test_limited_ack_skips_handler() {
+        use crate::connections::mock;
+        use crate::server::server::Server;
+        use flare_core::flare_net::net::{Message as ProtoMessage, ResCode, Response};
+        use prost::Message;
+
+        let server = Arc::new(Server::default().with_rate_limit(RateLimitConfig::new()
+            .with_connection_limit(Rate::new(1, 0.0))));
+        let mut peer = mock::login_default(server).await;
+        for i in 0..3 {
+            peer.tx.send(ProtoMessage {
+                command: Command::ClientAck as i32,
+                data: format!("msg-{}", i).into_bytes(),
+                client_id: format!("a{}", i),
+                ..Default::default()
+            }).unwrap();
+        }
+        // 只有第一个 ACK 交给业务处理器，被限流的 ACK 不回复
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(reply.client_id, "a0");
+        assert_ne!(Response::decode(&reply.data[..]).unwrap().code, ResCode::RateLimited as i32);
+        assert!(peer.recv_timeout(Duration::from_millis(100)).await.is_none());
+    }
+}
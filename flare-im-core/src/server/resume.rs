@@ -0,0 +1,122 @@
+use flare_core::flare_net::net::Platform;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// 可恢复的会话
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResumeSession {
+    pub user_id: String,
+    pub platform: Platform,
+    pub client_id: String,
+    pub language: String,
+    /// 连接断开时保存的状态，恢复后还原到新连接
+    pub state: SessionState,
+}
+
+/// 连接断开时的会话状态
+///
+/// 连接序列号不随会话恢复，客户端每次连接都从头计数，新连接同样从 1 开始推送
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionState {
+    /// 所在的房间
+    pub rooms: Vec<String>,
+    /// 订阅了在线状态的用户
+    pub subscriptions: Vec<String>,
+    pub background: bool,
+}
+
+struct Entry {
+    session: ResumeSession,
+    // 连接断开后的过期时间，连接存活时为 None
+    expires_at: Option<Instant>,
+}
+
+/// 会话恢复令牌
+///
+/// 登录成功后签发，连接断开后在 `window` 内可凭令牌免认证恢复会话。
+/// 令牌只能使用一次，恢复时换发新令牌，0-RTT 重放的登录请求因此会失效。
+#[derive(Clone)]
+pub struct ResumeTokens {
+    window: Duration,
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl ResumeTokens {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 为已认证的连接签发令牌
+    pub async fn issue(&self, session: ResumeSession) -> String {
+        let token = uuid::Uuid::new_v4().to_string();
+        let mut entries = self.entries.lock().await;
+        let now = Instant::now();
+        entries.retain(|_, e| e.expires_at.is_none_or(|t| t > now));
+        entries.insert(token.clone(), Entry { session, expires_at: None });
+        token
+    }
+
+    /// 使用令牌，令牌有效时返回会话并作废令牌
+    pub async fn take(&self, token: &str) -> Option<ResumeSession> {
+        let entry = self.entries.lock().await.remove(token)?;
+        match entry.expires_at {
+            Some(t) if t <= Instant::now() => None,
+            _ => Some(entry.session),
+        }
+    }
+
+    /// 作废令牌，例如连接被踢下线
+    pub async fn revoke(&self, token: &str) {
+        self.entries.lock().await.remove(token);
+    }
+
+    /// 连接断开，保存会话状态，令牌在恢复窗口后过期
+    pub async fn detach(&self, token: &str, state: SessionState) {
+        if let Some(entry) = self.entries.lock().await.get_mut(token) {
+            entry.session.state = state;
+            entry.expires_at = Some(Instant::now() + self.window);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock::{self, DEFAULT_USER};
+    use crate::server::server::Server;
+    use flare_core::context::RoomOps;
+    use flare_core::flare_net::net::{LoginReq, Message as ProtoMessage};
+
+    #[tokio::test]
+    async fn test_resume_without_auth() {
+        let server = Arc::new(Server::default().with_resume_window(Duration::from_secs(60)));
+        let (mut peer, resp) = mock::login_with_resp(server.clone(), mock::login_req()).await;
+        assert!(!resp.resume_token.is_empty());
+        let conn_id = server.get_user_connections(DEFAULT_USER).await[0].get_conn_id();
+        server.rooms().join("room", &conn_id).await.unwrap();
+        server.presence().subscribe(DEFAULT_USER, &["bob".to_string()]).await;
+        server.send_to_user(DEFAULT_USER, ProtoMessage::default()).await.unwrap();
+        assert_eq!(peer.recv_timeout(Duration::from_secs(1)).await.unwrap().seq, 1);
+        // 断开连接
+        drop(peer);
+        assert!(mock::eventually(|| async { server.get_user_connections(DEFAULT_USER).await.is_empty() }).await);
+
+        // 不带认证令牌也能恢复，房间和订阅随会话还原，令牌换发后旧令牌失效
+        let (mut peer, resumed) = mock::login_with_resp(server.clone(), LoginReq { resume_token: resp.resume_token.clone(), ..Default::default() }).await;
+        assert_eq!(resumed.user_id, DEFAULT_USER);
+        assert_ne!(resumed.resume_token, resp.resume_token);
+        let conn_id = server.get_user_connections(DEFAULT_USER).await[0].get_conn_id();
+        assert_eq!(server.rooms().rooms_of(&conn_id).await, vec!["room".to_string()]);
+        assert_eq!(server.presence().subscriptions_of(DEFAULT_USER).await, vec!["bob".to_string()]);
+        // 连接序列号和客户端一样从头开始
+        server.send_to_user(DEFAULT_USER, ProtoMessage::default()).await.unwrap();
+        assert_eq!(peer.recv_timeout(Duration::from_secs(1)).await.unwrap().seq, 1);
+        let (_peer, replay) = mock::login_with_resp(server.clone(), LoginReq { resume_token: resp.resume_token, ..Default::default() }).await;
+        assert!(replay.user_id.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::server::server::ConnectionInfo;
+use async_trait::async_trait;
+use flare_core::context::RoomOps;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::Message as ProtoMessage;
+use futures::future::join_all;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Default)]
+struct Membership {
+    rooms: HashMap<String, HashMap<String, String>>, // room_id -> (conn_id -> user_id)
+    conn_rooms: HashMap<String, HashSet<String>>,     // conn_id -> room_ids
+}
+
+/// 本节点的房间表，成员以连接为单位，连接断开时自动离开所有房间
+#[derive(Clone)]
+pub struct Rooms {
+    membership: Arc<Mutex<Membership>>,
+    connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>,
+}
+
+impl Rooms {
+    pub(crate) fn new(connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>) -> Self {
+        Self {
+            membership: Arc::new(Mutex::new(Membership::default())),
+            connections,
+        }
+    }
+
+    /// 连接离开所有房间
+    pub async fn leave_all(&self, conn_id: &str) {
+        let mut membership = self.membership.lock().await;
+        if let Some(room_ids) = membership.conn_rooms.remove(conn_id) {
+            for room_id in room_ids {
+                Self::remove_member(&mut membership, &room_id, conn_id);
+            }
+        }
+    }
+
+    /// 连接所在的房间
+    pub async fn rooms_of(&self, conn_id: &str) -> Vec<String> {
+        let membership = self.membership.lock().await;
+        membership.conn_rooms.get(conn_id)
+            .map(|rooms| rooms.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn remove_member(membership: &mut Membership, room_id: &str, conn_id: &str) {
+        if let Some(members) = membership.rooms.get_mut(room_id) {
+            members.remove(conn_id);
+            if members.is_empty() {
+                membership.rooms.remove(room_id);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RoomOps for Rooms {
+    async fn join(&self, room_id: &str, conn_id: &str) -> Result<()> {
+        let user_id = match self.connections.lock().await.get(conn_id) {
+            Some(info) => info.get_user_id(),
+            None => return Err(FlareErr::ConnectionNotFound),
+        };
+        let mut membership = self.membership.lock().await;
+        membership.rooms.entry(room_id.to_string()).or_default().insert(conn_id.to_string(), user_id);
+        membership.conn_rooms.entry(conn_id.to_string()).or_default().insert(room_id.to_string());
+        Ok(())
+    }
+
+    async fn leave(&self, room_id: &str, conn_id: &str) -> Result<()> {
+        let mut membership = self.membership.lock().await;
+        Self::remove_member(&mut membership, room_id, conn_id);
+        if let Some(rooms) = membership.conn_rooms.get_mut(conn_id) {
+            rooms.remove(room_id);
+            if rooms.is_empty() {
+                membership.conn_rooms.remove(conn_id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn members(&self, room_id: &str) -> Vec<String> {
+        let membership = self.membership.lock().await;
+        let mut users: Vec<String> = membership.rooms.get(room_id)
+            .map(|members| members.values().cloned().collect())
+            .unwrap_or_default();
+        users.sort();
+        users.dedup();
+        users
+    }
+
+    async fn send(&self, room_id: &str, msg: ProtoMessage, exclude_user: Option<&str>) -> Result<usize> {
+        let conn_ids: Vec<String> = {
+            let membership = self.membership.lock().await;
+            match membership.rooms.get(room_id) {
+                Some(members) => members.iter()
+                    .filter(|(_, user_id)| Some(user_id.as_str()) != exclude_user)
+                    .map(|(conn_id, _)| conn_id.clone())
+                    .collect(),
+                None => return Ok(0),
+            }
+        };
+        // 只在取连接时持锁，发送并发进行
+        let targets: Vec<ConnectionInfo> = {
+            let conns = self.connections.lock().await;
+            conn_ids.iter().filter_map(|conn_id| conns.get(conn_id).cloned()).collect()
+        };
+        let results = join_all(targets.iter().map(|info| info.push(msg.clone()))).await;
+        let mut sent = 0;
+        for (info, result) in targets.iter().zip(results) {
+            match result {
+                Ok(()) => sent += 1,
+                Err(e) => warn!("Failed to send room {} message to {}: {}", room_id, info.get_conn_id(), e),
+            }
+        }
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock::{self, DEFAULT_USER};
+    use crate::server::server::Server;
+    use flare_core::flare_net::net::Command;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_room_fan_out() {
+        let server = Arc::new(Server::default());
+        let mut peer1 = mock::login_default(server.clone()).await;
+        let mut peer2 = mock::login_default(server.clone()).await;
+        let conn_ids: Vec<String> = server.get_user_connections(DEFAULT_USER).await
+            .iter().map(|info| info.get_conn_id()).collect();
+        assert_eq!(conn_ids.len(), 2);
+        for conn_id in &conn_ids {
+            server.join_room("room-1", conn_id).await.unwrap();
+        }
+        assert!(server.join_room("room-1", "missing").await.is_err());
+        assert_eq!(server.room_members("room-1").await, vec![DEFAULT_USER.to_string()]);
+
+        let msg = ProtoMessage {
+            command: Command::ServerPushMsg as i32,
+            data: b"hi".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(server.send_to_room("room-1", msg.clone(), None).await.unwrap(), 2);
+        for peer in [&mut peer1, &mut peer2] {
+            assert_eq!(peer.recv_timeout(Duration::from_secs(1)).await.unwrap().data, b"hi".to_vec());
+        }
+        assert_eq!(server.send_to_room("room-1", msg.clone(), Some(DEFAULT_USER)).await.unwrap(), 0);
+
+        // 断开连接后自动离开房间
+        drop(peer1);
+        assert!(mock::eventually(|| async { server.rooms().rooms_of(&conn_ids[0]).await.is_empty() }).await);
+        assert_eq!(server.send_to_room("room-1", msg, None).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::server::handlers::CommandHandler;
+use async_trait::async_trait;
+use flare_core::context::AppContext;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::{Command, ResCode, Response};
+use futures::future::BoxFuture;
+use prost::Message;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+type RouteHandler = Arc<dyn Fn(AppContext) -> BoxFuture<'static, Result<Response>> + Send + Sync>;
+
+/// 按消息中的 `route` 分发的业务路由
+///
+/// 处理器的请求和响应都是 protobuf 消息，由路由自动解码 `AppContext::data` 并编码响应。
+/// 数值路由使用其十进制字符串注册，例如 `"1001"`。
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: HashMap<String, RouteHandler>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册路由，重复注册时覆盖之前的处理器
+    pub fn route<T, R, F, Fut>(mut self, route: impl Into<String>, handler: F) -> Self
+    where
+        T: Message + Default + 'static,
+        R: Message + 'static,
+        F: Fn(AppContext, T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.routes.insert(route.into(), Arc::new(move |ctx: AppContext| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                let req = match ctx.get_data_as::<T>() {
+                    Ok(req) => req,
+                    Err(e) => return Ok(e.to_res()),
+                };
+                Ok(match handler(ctx, req).await {
+                    Ok(resp) => Response {
+                        code: ResCode::Success as i32,
+                        message: String::new(),
+                        data: resp.encode_to_vec(),
+                    },
+                    Err(e) => e.to_res(),
+                })
+            })
+        }));
+        self
+    }
+
+    /// 是否注册了路由
+    pub fn has_route(&self, route: &str) -> bool {
+        self.routes.contains_key(route)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+#[async_trait]
+impl CommandHandler for Router {
+    async fn handle_command(&self, ctx: &AppContext) -> Result<Response> {
+        let route = ctx.route().unwrap_or_default();
+        match self.routes.get(&route) {
+            Some(handler) => handler(ctx.clone()).await,
+            None => Err(FlareErr::NotFondHandler),
+        }
+    }
+
+    /// 路由不区分命令
+    fn supported_commands(&self) -> Vec<Command> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock;
+    use crate::server::server::Server;
+    use flare_core::flare_net::net::{LoginReq, LoginResp, Message as ProtoMessage, Presence};
+    use std::time::Duration;
+
+    async fn request(peer: &mut mock::MockPeer, route: &str, data: Vec<u8>) -> Response {
+        peer.tx.send(ProtoMessage {
+            command: Command::ClientRequest as i32,
+            route: route.into(),
+            data,
+            ..Default::default()
+        }).unwrap();
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        Response::decode(&reply.data[..]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_typed_route() {
+        let router = Router::new()
+            .route("user.profile", |ctx: AppContext, req: LoginReq| async move {
+                Ok(Presence { user_id: format!("{}:{}", ctx.user_id().unwrap_or_default(), req.user_id), ..Default::default() })
+            })
+            .route("1001", |_ctx: AppContext, _req: LoginResp| async move {
+                Err::<LoginResp, _>(FlareErr::invalid_params("bad request"))
+            });
+        let server = Arc::new(Server::default().with_routes(router));
+        let mut peer = mock::login_default(server).await;
+
+        let resp = request(&mut peer, "user.profile", LoginReq { user_id: "bob".into(), ..Default::default() }.encode_to_vec()).await;
+        assert_eq!(resp.code, ResCode::Success as i32);
+        assert_eq!(Presence::decode(&resp.data[..]).unwrap().user_id, "sss:bob");
+
+        let resp = request(&mut peer, "1001", Vec::new()).await;
+        assert_eq!(resp.code, ResCode::InvalidParams as i32);
+
+        let resp = request(&mut peer, "user.profile", vec![0xff]).await;
+        assert_eq!(resp.code, ResCode::DecodeError as i32);
+
+        // 系统命令忽略路由，仍由系统处理器处理
+        peer.tx.send(ProtoMessage {
+            command: Command::SetBackground as i32,
+            route: "user.profile".into(),
+            data: vec![0xff],
+            ..Default::default()
+        }).unwrap();
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(Response::decode(&reply.data[..]).unwrap().code, ResCode::Success as i32);
+    }
+}
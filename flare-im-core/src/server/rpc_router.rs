@@ -0,0 +1,52 @@
+use crate::server::session_store::{LocalDelivery, NodeRouter};
+use async_trait::async_trait;
+use flare_core::error::Result;
+use flare_core::flare_net::net::Message as ProtoMessage;
+use flare_rpc_core::discover::RpcDiscovery;
+use flare_rpc_core::gateway::{GatewayPushServer, GatewayPushService, GatewayPusher, PushHandler};
+use std::sync::Arc;
+
+/// 基于 flare-rpc-core 网关推送服务的节点路由
+///
+/// 每个网关节点需以 `gateway_service_name(node_id)` 注册到注册中心
+pub struct RpcNodeRouter<D: RpcDiscovery> {
+    pusher: GatewayPusher<D>,
+}
+
+impl<D: RpcDiscovery> RpcNodeRouter<D> {
+    pub fn new(discovery: D) -> Self {
+        Self {
+            pusher: GatewayPusher::new(discovery),
+        }
+    }
+}
+
+#[async_trait]
+impl<D: RpcDiscovery> NodeRouter for RpcNodeRouter<D> {
+    async fn forward(&self, node_id: &str, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        self.pusher.push(node_id, user_id, msg).await
+    }
+}
+
+/// 网关推送处理器，把其它节点转发来的消息投递给本节点的连接
+pub struct GatewayPushHandler {
+    delivery: Arc<dyn LocalDelivery>,
+}
+
+impl GatewayPushHandler {
+    pub fn new(delivery: Arc<dyn LocalDelivery>) -> Self {
+        Self { delivery }
+    }
+
+    /// 创建可添加到 tonic server 的网关推送服务
+    pub fn into_server(self) -> GatewayPushServer<GatewayPushService<Self>> {
+        GatewayPushService::new(self).into_server()
+    }
+}
+
+#[async_trait]
+impl PushHandler for GatewayPushHandler {
+    async fn push_to_user(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        self.delivery.deliver_local(user_id, msg).await
+    }
+}
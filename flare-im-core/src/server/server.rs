@@ -1,25 +1,44 @@
-use flare_core::context::{AppContext, AppContextBuilder};
+use flare_core::context::{AppContext, AppContextBuilder, RoomOps, TraceContext};
 use flare_core::error::{FlareErr, Result};
-use crate::connections::Connection;
+use crate::connections::{Connection, HandshakeInfo};
 use crate::server::handlers::{CommandHandler, ServerMessageHandler};
 use log::{debug, error, info, warn};
 use prost::Message;
-use flare_core::flare_net::net::LoginResp;
-use flare_core::flare_net::net::{Command, Message as ProtoMessage, Platform, ResCode, Response};
+use flare_core::flare_net::net::{LoginReq, LoginResp};
+use flare_core::flare_net::net::{CloseNotice, Command, Message as ProtoMessage, Platform, Presence, PresenceList, PresenceSubReq, PullReq, ResCode, Response};
+use std::any::Any;
+use std::future::Future;
 use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio::time::{interval, Duration};
 use crate::server::auth_handler::AuthHandler;
 use crate::server::server_handler::ServerHandler;
 use crate::server::sys_handler::SystemHandler;
+use crate::server::session_store::{LocalDelivery, LocalRouter, MemorySessionStore, NodeRouter, SessionRoute, SessionStore};
+use crate::server::delivery::{DeliveryConfig, DeliveryTracker, Undelivered};
+use crate::server::offline::{OfflineStore, DEFAULT_PULL_LIMIT, MAX_PULL_LIMIT};
+use crate::server::room::Rooms;
+use crate::server::presence::PresenceHub;
+use crate::server::login_policy::LoginPolicy;
+use crate::server::lifecycle::{ConnectionEvent, DisconnectReason, EVENT_CAPACITY};
+use crate::server::heartbeat::HeartbeatPolicy;
+use crate::server::metrics;
+use crate::server::resume::{ResumeSession, ResumeTokens, SessionState};
+use crate::server::middleware::Middleware;
+use crate::server::router::Router;
+use crate::server::rate_limit::{RateDecision, RateLimitConfig, RateLimiter};
+use crate::server::shutdown::InFlight;
+use async_trait::async_trait;
+use futures::future::join_all;
+use futures::FutureExt;
+use tracing::Instrument;
 
 use super::auth_handler::DefAuthHandler;
 use super::server_handler::DefServerHandler;
 use super::sys_handler::DefSystemHandler;
 
-const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
-const CONNECTION_TIMEOUT: Duration = Duration::from_secs(90);
 
 /// 连接信息
 #[derive(Clone)]
@@ -31,8 +50,12 @@ pub struct ConnectionInfo {
     language: Option<String>,
     client_id : String,
     remote_addr: String,
+    handshake: Option<HandshakeInfo>,
+    resume_token: Option<String>,
     connected_at: chrono::DateTime<chrono::Utc>,
     last_heartbeat: Arc<Mutex<chrono::DateTime<chrono::Utc>>>,
+    seq: Arc<Mutex<u64>>, // 最近一次推送的连接序列号
+    background: Arc<Mutex<bool>>,
     conn: Arc<Box<dyn Connection>>,
 }
 
@@ -53,14 +76,38 @@ impl ConnectionInfo {
             client_id,
             remote_addr,
             protocol,
+            handshake: conn.handshake().cloned(),
+            resume_token: None,
             connected_at: chrono::Utc::now(),
             last_heartbeat: Arc::new(Mutex::new(chrono::Utc::now())),
+            seq: Arc::new(Mutex::new(0)),
+            background: Arc::new(Mutex::new(false)),
             conn: Arc::new(conn),
         }
     }
 
     pub async fn send(&self, msg: ProtoMessage) -> Result<()> {
-        self.conn.send(msg).await
+        let command = msg.command;
+        let result = self.conn.send(msg).await;
+        metrics::message_out(command, result.is_ok());
+        result
+    }
+
+    /// 推送消息，按连接分配递增的序列号
+    pub async fn push(&self, mut msg: ProtoMessage) -> Result<()> {
+        // 持锁发送，保证序列号与发送顺序一致
+        let mut seq = self.seq.lock().await;
+        *seq += 1;
+        msg.seq = *seq;
+        let command = msg.command;
+        let result = self.conn.send(msg).await;
+        metrics::message_out(command, result.is_ok());
+        result
+    }
+
+    /// 最近一次推送的序列号
+    pub async fn last_seq(&self) -> u64 {
+        *self.seq.lock().await
     }
 
     pub async fn receive(&self) -> Result<ProtoMessage> {
@@ -73,9 +120,39 @@ impl ConnectionInfo {
     pub fn get_conn_id(&self) -> String {
         self.conn_id.clone()
     }
+    pub fn get_user_id(&self) -> String {
+        self.user_id.clone()
+    }
+    pub fn get_platform(&self) -> Platform {
+        self.platform
+    }
+    pub fn get_client_id(&self) -> String {
+        self.client_id.clone()
+    }
+    pub fn get_language(&self) -> Option<String> {
+        self.language.clone()
+    }
+    /// 最近一次收到消息的时间
+    pub async fn get_last_heartbeat(&self) -> chrono::DateTime<chrono::Utc> {
+        *self.last_heartbeat.lock().await
+    }
+    /// 是否在后台运行
+    pub async fn is_background(&self) -> bool {
+        *self.background.lock().await
+    }
+    pub async fn set_background(&self, background: bool) {
+        *self.background.lock().await = background;
+    }
     pub fn get_protocol(&self) -> String {
         self.protocol.clone()
     }
+    pub fn get_remote_addr(&self) -> String {
+        self.remote_addr.clone()
+    }
+    /// 握手信息，例如 WebSocket 升级请求的 User-Agent 和 X-Forwarded-For
+    pub fn get_handshake(&self) -> Option<&HandshakeInfo> {
+        self.handshake.as_ref()
+    }
     pub fn get_connection_at(&self) -> chrono::DateTime<chrono::Utc> {
         self.connected_at.clone()
     }
@@ -90,6 +167,22 @@ where
     handler: Arc<ServerMessageHandler<S, A, Y>>,
     connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>, // conn_id -> ConnectionInfo
     user_connections: Arc<Mutex<HashMap<String, Vec<String>>>>, // user_id -> Vec<conn_id>
+    node_id: String,
+    session_store: Arc<dyn SessionStore>,
+    router: Arc<dyn NodeRouter>,
+    tracker: DeliveryTracker,
+    undelivered_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<Undelivered>>>>,
+    offline_store: Option<Arc<dyn OfflineStore>>,
+    rooms: Rooms,
+    presence: PresenceHub,
+    login_policy: LoginPolicy,
+    rate_limiter: Option<RateLimiter>,
+    resume_tokens: Option<ResumeTokens>,
+    draining: Arc<Mutex<Option<CloseNotice>>>, // 停机时下发的关闭通知
+    in_flight: InFlight,
+    events: broadcast::Sender<ConnectionEvent>,
+    heartbeat: HeartbeatPolicy,
+    heartbeat_started: Arc<Mutex<bool>>,
 }
 
 impl<S, A, Y> Server<S, A, Y>
@@ -99,23 +192,160 @@ where
     Y: SystemHandler + Send + Sync + 'static,
 {
     pub fn new(handler: ServerMessageHandler<S, A, Y>) -> Self {
-        let server = Self {
+        let (tracker, undelivered_rx) = DeliveryTracker::new(DeliveryConfig::default());
+        let connections = Arc::new(Mutex::new(HashMap::new()));
+        let user_connections = Arc::new(Mutex::new(HashMap::new()));
+        Self {
             handler: Arc::new(handler),
-            connections: Arc::new(Mutex::new(HashMap::new())),
-            user_connections: Arc::new(Mutex::new(HashMap::new())),
-        };
+            rooms: Rooms::new(connections.clone()),
+            presence: PresenceHub::new(connections.clone(), Arc::clone(&user_connections)),
+            connections,
+            user_connections,
+            node_id: uuid::Uuid::new_v4().to_string(),
+            session_store: Arc::new(MemorySessionStore::new()),
+            router: Arc::new(LocalRouter::new()),
+            tracker,
+            undelivered_rx: Arc::new(Mutex::new(Some(undelivered_rx))),
+            offline_store: None,
+            login_policy: LoginPolicy::default(),
+            rate_limiter: None,
+            resume_tokens: None,
+            draining: Arc::new(Mutex::new(None)),
+            in_flight: InFlight::new(),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            heartbeat: HeartbeatPolicy::default(),
+            heartbeat_started: Arc::new(Mutex::new(false)),
+        }
+    }
+
+    /// 设置节点ID，集群内唯一
+    pub fn with_node_id(mut self, node_id: impl Into<String>) -> Self {
+        self.node_id = node_id.into();
+        self
+    }
+
+    /// 设置会话存储
+    pub fn with_session_store(mut self, store: impl SessionStore + 'static) -> Self {
+        self.session_store = Arc::new(store);
+        self
+    }
+
+    /// 设置跨节点路由
+    pub fn with_router(mut self, router: impl NodeRouter + 'static) -> Self {
+        self.router = Arc::new(router);
+        self
+    }
+
+    /// 设置可靠推送的 ACK 超时与重试策略
+    pub fn with_delivery_config(mut self, config: DeliveryConfig) -> Self {
+        self.tracker = self.tracker.with_config(config);
+        self
+    }
+
+    /// 设置离线消息存储
+    ///
+    /// 设置后用户不在线或可靠推送未确认的消息会保存到存储中，
+    /// 客户端登录后以空 `conversation_id` 的 `CLIENT_PULL_MESSAGE` 分页拉取
+    pub fn with_offline_store(mut self, store: impl OfflineStore + 'static) -> Self {
+        self.offline_store = Some(Arc::new(store));
+        self
+    }
+
+    /// 设置多端登录策略，仅作用于本节点上的连接
+    pub fn with_login_policy(mut self, policy: LoginPolicy) -> Self {
+        self.login_policy = policy;
+        self
+    }
+
+    /// 添加命令处理中间件，先添加的在外层
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.get_handler_mut().add_middleware(middleware);
+        self
+    }
 
-        // 启动心跳检测
-        let connections = server.connections.clone();
+    /// 设置按 `route` 分发的业务路由
+    pub fn with_routes(mut self, router: Router) -> Self {
+        self.get_handler_mut().set_routes(router);
+        self
+    }
+
+    /// 设置请求限流，心跳不受限制
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(config));
+        self
+    }
+
+    /// 设置心跳策略，心跳间隔在登录响应中下发给客户端
+    pub fn with_heartbeat_policy(mut self, policy: HeartbeatPolicy) -> Self {
+        self.heartbeat = policy;
+        self
+    }
+
+    /// 开启会话恢复，连接断开后 `window` 内可凭登录响应中的 `resume_token` 免认证恢复会话
+    pub fn with_resume_window(mut self, window: Duration) -> Self {
+        self.resume_tokens = Some(ResumeTokens::new(window));
+        self
+    }
+
+    /// 订阅连接生命周期事件
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
+    /// 获取节点ID
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// 本节点的投递器，供集群路由转发消息时使用
+    pub fn local_delivery(&self) -> Arc<dyn LocalDelivery> {
+        Arc::new(self.local())
+    }
+
+    fn local(&self) -> LocalConnections {
+        LocalConnections {
+            connections: self.connections.clone(),
+            user_connections: self.user_connections.clone(),
+            tracker: self.tracker.clone(),
+        }
+    }
+
+    /// 首个连接建立时启动心跳检测
+    async fn start_heartbeat_checker(&self) {
+        let mut started = self.heartbeat_started.lock().await;
+        if *started {
+            return;
+        }
+        *started = true;
+        let handle = self.handle();
         tokio::spawn(async move {
-            let mut interval = interval(HEARTBEAT_INTERVAL);
+            let mut interval = interval(handle.heartbeat.check_period());
             loop {
                 interval.tick().await;
-                Self::check_connections(connections.clone()).await;
+                handle.check_connections().await;
             }
         });
+    }
 
-        server
+    /// 启动投递失败上报任务，首个连接建立时启动
+    async fn start_undelivered_worker(&self) {
+        if let Some(mut rx) = self.undelivered_rx.lock().await.take() {
+            let handler = self.handler.clone();
+            let offline_store = self.offline_store.clone();
+            tokio::spawn(async move {
+                while let Some(undelivered) = rx.recv().await {
+                    warn!("Message {} undelivered to {}", undelivered.msg.server_msg_id, undelivered.user_id);
+                    if let Some(store) = &offline_store {
+                        if let Err(e) = store.append(&undelivered.user_id, undelivered.msg.clone()).await {
+                            error!("Failed to store offline message for {}: {}", undelivered.user_id, e);
+                        }
+                    }
+                    if let Err(e) = handler.handle_undelivered(&undelivered.user_id, &undelivered.msg).await {
+                        error!("Failed to handle undelivered message: {}", e);
+                    }
+                }
+            });
+        }
     }
 
     /// 添加新连接
@@ -123,22 +353,58 @@ where
         let conn_id = conn.id().to_string();
         let remote_addr = conn.remote_addr().to_string();
         info!("New connection from {}: {}", remote_addr, conn_id);
+        if let Some(notice) = self.draining.lock().await.clone() {
+            // 停机中不再接受新连接
+            Self::close_with_notice(conn.as_ref(), notice).await;
+            return;
+        }
+        self.start_undelivered_worker().await;
+        self.start_heartbeat_checker().await;
+        if let Err(reason) = catch_panic(self.handler.on_connected(&conn_id, &remote_addr)).await {
+            error!("on_connected panicked for {}: {}", conn_id, reason);
+        }
+        let _ = self.events.send(ConnectionEvent::Connected {
+            conn_id: conn_id.clone(),
+            remote_addr: remote_addr.clone(),
+            protocol: conn.protocol().to_string(),
+        });
         // 等待认证消息
         match self.wait_for_auth(&conn).await {
-            Ok(login_resp) => {
-                let info = ConnectionInfo::new(
+            Ok((login_req, login_resp, state)) => {
+                metrics::auth(true);
+                // 优先使用登录请求中的平台
+                let platform = match Platform::try_from(login_req.platform) {
+                    Ok(Platform::Unknown) | Err(_) => conn.platform(),
+                    Ok(platform) => platform,
+                };
+                let superseded = self.superseded_connections(&login_resp.user_id, platform).await;
+                let mut info = ConnectionInfo::new(
                     conn.clone_box(),
                     login_resp.user_id.clone(),
-                    conn.platform(),
+                    platform,
                     conn.id().to_string(),
                     conn.remote_addr().to_string(),
                     conn.protocol().to_string(),
                 );
+                if !login_resp.resume_token.is_empty() {
+                    info.resume_token = Some(login_resp.resume_token.clone());
+                }
+                // 恢复的会话沿用断开前的后台状态
+                *info.background.lock().await = state.background;
 
                 // 保存连接信息
                 {
                     let mut conns = self.connections.lock().await;
+                    // 持有连接表时再检查停机状态，认证期间开始的停机不会漏掉这个连接
+                    if let Some(notice) = self.draining.lock().await.clone() {
+                        drop(conns);
+                        info!("Server draining, close connection {} after auth", conn_id);
+                        Self::close_with_notice(conn.as_ref(), notice).await;
+                        return;
+                    }
                     conns.insert(conn_id.clone(), info.clone());
+                    // 和连接表同步计数，连接移除时对应减少
+                    metrics::connection_opened(&info.protocol, info.platform);
                     
                     // 处理新连接
                     let ctx = match self.build_context(
@@ -169,11 +435,15 @@ where
                             if let Err(e) = conn.close().await {
                                 error!("Failed to close connection: {}", e);
                             }
+                            drop(conns);
+                            self.discard_connection(info).await;
                             return;
                         }
                     };
                         
-                    if let Err(e) = self.handler.handle_new_connection(&ctx, &info).await {
+                    let result = catch_panic(self.handler.handle_new_connection(&ctx, &info)).await
+                        .unwrap_or_else(|reason| Err(FlareErr::internal_error(format!("handler panicked: {}", reason))));
+                    if let Err(e) = result {
                         error!("Failed to handle new connection: {}", e);
                         // 发送错误响应
                         if let Err(send_err) = conn.send(ProtoMessage {
@@ -191,6 +461,8 @@ where
                         if let Err(close_err) = conn.close().await {
                             error!("Failed to close connection: {}", close_err);
                         }
+                        drop(conns);
+                        self.discard_connection(info).await;
                         return;
                     }
                 }
@@ -198,16 +470,45 @@ where
                 // 更新用户连接映射
                 {
                     let mut user_conns = self.user_connections.lock().await;
-                    user_conns.entry(login_resp.user_id)
+                    user_conns.entry(login_resp.user_id.clone())
                         .or_insert_with(Vec::new)
                         .push(conn_id.clone());
                 }
+                // 恢复的会话重新加入断开前的房间和在线状态订阅
+                for room_id in &state.rooms {
+                    if let Err(e) = self.rooms.join(room_id, &conn_id).await {
+                        warn!("Failed to rejoin room {} for {}: {}", room_id, conn_id, e);
+                    }
+                }
+                if !state.subscriptions.is_empty() {
+                    self.presence.subscribe(&login_resp.user_id, &state.subscriptions).await;
+                }
+
+                // 注册会话路由
+                if let Err(e) = self.session_store.register(&login_resp.user_id, SessionRoute {
+                    node_id: self.node_id.clone(),
+                    conn_id: conn_id.clone(),
+                    platform: info.platform,
+                }).await {
+                    error!("Failed to register session for {}: {}", login_resp.user_id, e);
+                }
+                self.presence.refresh(&login_resp.user_id).await;
+                if let Err(reason) = catch_panic(self.handler.on_authenticated(&info)).await {
+                    error!("on_authenticated panicked for {}: {}", conn_id, reason);
+                }
+                let _ = self.events.send(ConnectionEvent::Authenticated(info.clone()));
+
+                // 新连接注册完成后再踢掉被顶替的连接，避免在线状态抖动
+                for old_conn_id in superseded {
+                    self.kick_connection(&login_resp.user_id, &old_conn_id, "Logged in on another device").await;
+                }
 
                 // 启动消息处理
                 self.handle_connection(info).await;
             }
             Err(e) => {
                 error!("Authentication failed for {}: {}", remote_addr, e);
+                metrics::auth(false);
                 
                 // 发送认证失败响应
                 if let Err(send_err) = conn.send(ProtoMessage {
@@ -230,6 +531,17 @@ where
         }
     }
 
+    /// 认证后初始化失败时移除已登记的连接，登录被拒绝，不保存恢复状态
+    async fn discard_connection(&self, info: ConnectionInfo) {
+        self.connections.lock().await.remove(&info.conn_id);
+        metrics::connection_closed(&info.protocol, info.platform);
+        if let (Some(tokens), Some(token)) = (&self.resume_tokens, &info.resume_token) {
+            tokens.revoke(token).await;
+        }
+        // 未回调 on_authenticated，这里也不回调 on_disconnected
+        let _ = self.events.send(ConnectionEvent::Disconnected { info, reason: DisconnectReason::Error });
+    }
+
     /// 构建应用上下文
     async fn build_context(
         &self,
@@ -237,7 +549,11 @@ where
         conn_id: String,
         client_msg_id: String,
     ) -> Option<AppContext> {
-        match builder.build() {
+        match builder
+            .with_conn_id(conn_id.clone())
+            .with_rooms(Arc::new(self.rooms.clone()))
+            .build()
+        {
             Ok(ctx) => Some(ctx),
             Err(e) => {
                 error!("Failed to build context: {}", e);
@@ -258,7 +574,21 @@ where
     }
 
     /// 等待认证消息
-    async fn wait_for_auth(&self, conn: &Box<dyn Connection>) -> Result<LoginResp> {
+    async fn wait_for_auth(&self, conn: &Box<dyn Connection>) -> Result<(LoginReq, LoginResp, SessionState)> {
+        // 握手时已携带令牌和平台则直接认证，无需等待登录消息。
+        // 缺少平台时仍等待登录消息，否则连接绕过按平台的登录策略和心跳间隔
+        if let Some(handshake) = conn.handshake() {
+            if let (Some(token), Some(platform)) = (&handshake.token, handshake.platform) {
+                let req = LoginReq {
+                    token: token.clone(),
+                    platform: platform as i32,
+                    client_id: handshake.client_id.clone().unwrap_or_default(),
+                    ..Default::default()
+                };
+                return self.authenticate(conn.as_ref(), req.encode_to_vec(), String::new()).await;
+            }
+        }
+
         let timeout = tokio::time::sleep(Duration::from_secs(30));
         tokio::pin!(timeout);
 
@@ -286,32 +616,7 @@ where
                                 }
                                 Ok(Command::Login) => {
                                     // 处理登录请求
-                                    let ctx = AppContextBuilder::new()
-                                        .remote_addr(conn.remote_addr().to_string())
-                                        .command(Some(Command::Login))
-                                        .data(msg.data)
-                                        .build()?;
-
-                                    match self.handler.handle_auth(&ctx).await {
-                                        Ok(response) => {
-                                            // 发送登录响应
-                                            conn.send(ProtoMessage {
-                                                command: Command::ServerResponse as i32,
-                                                data: response.data.clone(),
-                                                client_id: msg.client_id,
-                                                ..Default::default()
-                                            }).await?;
-
-                                            // 解析登录响应
-                                            if response.code == ResCode::Success as i32 {
-                                                if let Ok(login_resp) = LoginResp::decode(&response.data[..]) {
-                                                    return Ok(login_resp);
-                                                }
-                                            }
-                                            return Err(FlareErr::AuthError(response.message));
-                                        }
-                                        Err(e) => return Err(e),
-                                    }
+                                    return self.authenticate(conn.as_ref(), msg.data, msg.client_id).await;
                                 }
                                 _ => {
                                     warn!("Unexpected command during auth: {:?}", msg.command);
@@ -329,20 +634,166 @@ where
         }
     }
 
+    /// 使用登录请求认证连接并回复登录响应
+    ///
+    /// 开启会话恢复时，携带有效 `resume_token` 的请求跳过认证处理器直接恢复会话，并返回断开时保存的状态。
+    /// 其他登录请求要等握手确认后才认证，0-RTT 早期数据中的登录请求可能被重放
+    async fn authenticate(&self, conn: &dyn Connection, data: Vec<u8>, client_id: String) -> Result<(LoginReq, LoginResp, SessionState)> {
+        let mut req = LoginReq::decode(&data[..]).unwrap_or_default();
+        if let Some(tokens) = &self.resume_tokens {
+            if !req.resume_token.is_empty() {
+                if let Some(mut session) = tokens.take(&req.resume_token).await {
+                    info!("Resumed session of {} from {}", session.user_id, conn.remote_addr());
+                    req.user_id = session.user_id.clone();
+                    req.platform = session.platform as i32;
+                    req.client_id = session.client_id.clone();
+                    let mut login_resp = LoginResp {
+                        user_id: session.user_id.clone(),
+                        language: session.language.clone(),
+                        ..Default::default()
+                    };
+                    self.heartbeat.apply(&mut login_resp, session.platform);
+                    let state = std::mem::take(&mut session.state);
+                    login_resp.resume_token = tokens.issue(session).await;
+                    conn.send(ProtoMessage {
+                        command: Command::ServerResponse as i32,
+                        data: login_resp.encode_to_vec(),
+                        client_id,
+                        ..Default::default()
+                    }).await?;
+                    return Ok((req, login_resp, state));
+                }
+                debug!("Invalid or expired resume token from {}", conn.remote_addr());
+            }
+        }
+        if !conn.handshake_confirmed().await {
+            return Err(FlareErr::ConnectionError("Connection closed before handshake confirmed".to_string()));
+        }
+
+        let ctx = AppContextBuilder::new()
+            .remote_addr(conn.remote_addr().to_string())
+            .command(Some(Command::Login))
+            .data(data)
+            .build()?;
+
+        // 认证处理器 panic 时按内部错误回复登录失败
+        let mut response = catch_panic(self.handler.handle_auth(&ctx)).await
+            .unwrap_or_else(|reason| {
+                error!("Auth handler panicked for {}: {}", conn.remote_addr(), reason);
+                Err(FlareErr::internal_error(format!("auth handler panicked: {}", reason)))
+            })?;
+        let mut login_resp = None;
+        if response.code == ResCode::Success as i32 {
+            if let Ok(mut resp) = LoginResp::decode(&response.data[..]) {
+                let platform = match Platform::try_from(req.platform) {
+                    Ok(Platform::Unknown) | Err(_) => conn.platform(),
+                    Ok(platform) => platform,
+                };
+                self.heartbeat.apply(&mut resp, platform);
+                if let Some(tokens) = &self.resume_tokens {
+                    resp.resume_token = tokens.issue(ResumeSession {
+                        user_id: resp.user_id.clone(),
+                        platform,
+                        client_id: req.client_id.clone(),
+                        language: resp.language.clone(),
+                        ..Default::default()
+                    }).await;
+                }
+                response.data = resp.encode_to_vec();
+                login_resp = Some(resp);
+            }
+        }
+        // 发送登录响应
+        conn.send(ProtoMessage {
+            command: Command::ServerResponse as i32,
+            data: response.data.clone(),
+            client_id,
+            ..Default::default()
+        }).await?;
+
+        match login_resp {
+            Some(login_resp) => Ok((req, login_resp, SessionState::default())),
+            None => Err(FlareErr::AuthError(response.message)),
+        }
+    }
+
+    fn handle(&self) -> ServerHandle<S, A, Y> {
+        ServerHandle {
+            handler: self.handler.clone(),
+            connections: self.connections.clone(),
+            user_connections: self.user_connections.clone(),
+            session_store: self.session_store.clone(),
+            tracker: self.tracker.clone(),
+            offline_store: self.offline_store.clone(),
+            rooms: self.rooms.clone(),
+            presence: self.presence.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            resume_tokens: self.resume_tokens.clone(),
+            in_flight: self.in_flight.clone(),
+            draining: self.draining.clone(),
+            events: self.events.clone(),
+            heartbeat: self.heartbeat.clone(),
+        }
+    }
+
+    /// 按登录策略计算被新连接顶替的连接
+    async fn superseded_connections(&self, user_id: &str, platform: Platform) -> Vec<String> {
+        if self.login_policy == LoginPolicy::AllowAll {
+            return Vec::new();
+        }
+        let mut existing: Vec<ConnectionInfo> = self.get_user_connections(user_id).await;
+        existing.sort_by_key(|info| info.connected_at);
+        let existing: Vec<(String, Platform)> = existing.into_iter()
+            .map(|info| (info.conn_id, info.platform))
+            .collect();
+        self.login_policy.superseded(&existing, platform)
+    }
+
+    /// 通知连接被踢下线并关闭
+    async fn kick_connection(&self, user_id: &str, conn_id: &str, reason: &str) {
+        let info = self.connections.lock().await.get(conn_id).cloned();
+        // 先移除再关闭，断开原因不会被接收任务覆盖
+        self.handle().remove_connection(user_id, conn_id, DisconnectReason::Kicked).await;
+        if let Some(info) = info {
+            info!("Kick connection {} of user {}: {}", conn_id, user_id, reason);
+            // 被踢下线的会话不可恢复
+            if let (Some(tokens), Some(token)) = (&self.resume_tokens, &info.resume_token) {
+                tokens.revoke(token).await;
+            }
+            if let Err(e) = info.send(ProtoMessage {
+                command: Command::KickOnline as i32,
+                data: reason.into(),
+                ..Default::default()
+            }).await {
+                warn!("Failed to send kick notice to {}: {}", conn_id, e);
+            }
+            if let Err(e) = info.close().await {
+                warn!("Failed to close kicked connection {}: {}", conn_id, e);
+            }
+        }
+    }
+
     /// 处理连接
     async fn handle_connection(&self, info: ConnectionInfo) {
         let conn_id = info.conn_id.clone();
         let last_heartbeat = info.last_heartbeat.clone();
-        let handler = self.handler.clone();
-        let connections = self.connections.clone();
         let info = info.clone();
-        let server = Arc::new(ServerHandle {
-            handler,
-            connections,
-        });
+        let server = Arc::new(self.handle());
 
         tokio::spawn(async move {
-            while let Ok(msg) = info.receive().await {
+            let reason = loop {
+                let msg = match info.receive().await {
+                    Ok(msg) => {
+                        metrics::message_in(msg.command);
+                        msg
+                    }
+                    Err(FlareErr::ConnectionClosed) => break DisconnectReason::ClientClose,
+                    Err(e) => {
+                        debug!("Failed to receive from {}: {}", info.conn_id, e);
+                        break DisconnectReason::Error;
+                    }
+                };
+                let _in_flight = server.in_flight.enter();
                 debug!("Received message from {}: {:?}", info.remote_addr, msg);
                 *last_heartbeat.lock().await = chrono::Utc::now();
 
@@ -361,6 +812,65 @@ where
                             debug!("Received pong during auth, ignoring");
                             continue;
                         }
+                        if comm == Command::ClientAck {
+                            // 先确认可靠推送再限流，被限流的客户端不会因此触发重投
+                            if let Ok(msg_id) = String::from_utf8(msg.data.clone()) {
+                                server.tracker.ack(&info.user_id, &msg_id).await;
+                            }
+                        }
+                        if let Some(limiter) = &server.rate_limiter {
+                            let decision = limiter.check(&info.conn_id, &info.user_id, comm).await;
+                            if decision != RateDecision::Allow {
+                                warn!("Rate limited {:?} from {}", comm, info.conn_id);
+                                // 被限流的 ACK 不再交给业务处理器，也不回复
+                                if comm != Command::ClientAck {
+                                    let resp = FlareErr::rate_limited("Too many requests").to_res();
+                                    if let Err(e) = server.send_response(info.conn_id.clone(), msg.client_id, resp).await {
+                                        error!("Failed to send rate limited response: {}", e);
+                                        break DisconnectReason::Error;
+                                    }
+                                }
+                                if decision == RateDecision::Disconnect {
+                                    warn!("Disconnect abusive connection {}", info.conn_id);
+                                    if let Err(e) = info.close().await {
+                                        warn!("Failed to close connection {}: {}", info.conn_id, e);
+                                    }
+                                    break DisconnectReason::Kicked;
+                                }
+                                continue;
+                            }
+                        }
+                        if comm == Command::SubscribePresence {
+                            // 内置的在线状态订阅
+                            let resp = catch_panic(server.subscribe_presence(&info.user_id, &msg.data)).await
+                                .unwrap_or_else(|reason| {
+                                    error!("Presence subscription panicked for {}: {}", info.conn_id, reason);
+                                    FlareErr::internal_error("presence subscription failed").to_res()
+                                });
+                            if let Err(e) = server.send_response(info.conn_id.clone(), msg.client_id, resp).await {
+                                error!("Failed to send presence response: {}", e);
+                                break DisconnectReason::Error;
+                            }
+                            continue;
+                        }
+                        if comm == Command::ClientPullMessage {
+                            // 内置的离线消息拉取
+                            let resp = catch_panic(server.pull_offline(&info.user_id, &msg.data)).await
+                                .unwrap_or_else(|reason| {
+                                    error!("Offline pull panicked for {}: {}", info.conn_id, reason);
+                                    Some(FlareErr::internal_error("offline pull failed").to_res())
+                                });
+                            if let Some(resp) = resp {
+                                if let Err(e) = server.send_response(info.conn_id.clone(), msg.client_id, resp).await {
+                                    error!("Failed to send offline messages: {}", e);
+                                    break DisconnectReason::Error;
+                                }
+                                continue;
+                            }
+                        }
+                        // 沿用客户端传入的 trace，否则开启新的 trace
+                        let parent = TraceContext::parse(&msg.traceparent);
+                        let trace = parent.map(|parent| parent.child()).unwrap_or_else(TraceContext::new_root);
                         let ctx = match server.build_context(
                             AppContextBuilder::new()
                                 .user_id(info.user_id.clone())
@@ -369,32 +879,61 @@ where
                                 .platform(info.platform as i32)
                                 .data(msg.data.clone())
                                 .with_language(info.language.clone())
+                                .with_route(msg.route.clone())
+                                .with_trace(trace)
                                 .client_id(info.client_id.clone()),
                             info.conn_id.clone(),
                             msg.client_id.clone(),
                         ).await {
                             Some(ctx) => ctx,
-                            None => break,
+                            None => break DisconnectReason::Error,
                         };
 
-                        // 处理消息
-                        match server.handler.handle_command(&ctx).await {
+                        // 处理消息，处理器 panic 只影响当前消息
+                        let span = tracing::info_span!(
+                            "im.message",
+                            command = ?comm,
+                            user_id = %info.user_id,
+                            conn_id = %info.conn_id,
+                            trace_id = %trace.trace_id(),
+                            span_id = %trace.span_id(),
+                        );
+                        #[cfg(feature = "opentelemetry")]
+                        if let Some(parent) = &parent {
+                            parent.set_parent_of(&span);
+                        }
+                        let started = std::time::Instant::now();
+                        let result = match catch_panic(server.handler.handle_command(&ctx)).instrument(span).await {
+                            Ok(result) => result,
+                            Err(reason) => {
+                                error!("Handler panicked on {:?} from {}: {}", comm, info.conn_id, reason);
+                                Err(FlareErr::internal_error(format!("handler panicked: {}", reason)))
+                            }
+                        };
+                        metrics::handler_latency(comm, started.elapsed());
+                        match result {
                             Ok(response) => {
+                                if comm == Command::SetBackground && response.code == ResCode::Success as i32 {
+                                    if let Ok(background) = ctx.bool_data() {
+                                        info.set_background(background).await;
+                                        server.presence.refresh(&info.user_id).await;
+                                    }
+                                }
                                 if let Err(e) = server.send_response(info.conn_id.clone(), msg.client_id, response).await {
                                     error!("Failed to send response: {}", e);
-                                    break;
+                                    break DisconnectReason::Error;
                                 }
                             }
                             Err(e) => {
                                 error!("Message handling error: {}", e);
-                                if let Err(e) = server.send_response(info.conn_id.clone(), msg.client_id, Response {
-                                    code: ResCode::InternalError as i32,
-                                    message: e.to_string(),
-                                    data: Vec::new(),
-                                }).await {
-                                    error!("Failed to send error response: {}", e);
+                                if let Err(send_err) = server.send_response(info.conn_id.clone(), msg.client_id, e.to_res()).await {
+                                    error!("Failed to send error response: {}", send_err);
+                                    break DisconnectReason::Error;
+                                }
+                                // 连接本身出错时才断开，业务错误只返回错误码
+                                if matches!(e, FlareErr::ConnectionClosed | FlareErr::ConnectionNotFound | FlareErr::ConnectionError(_)) {
+                                    break DisconnectReason::Error;
                                 }
-                                break;
                             }
                         }
                     }
@@ -407,64 +946,172 @@ where
                         }).await {
                             error!("Failed to send invalid command response: {}", e);
                         }
-                        break;
+                        break DisconnectReason::Error;
                     }
                 }
-            }
+            };
 
-            let mut conns = server.connections.lock().await;
-            conns.remove(&conn_id);
+            let reason = if server.draining.lock().await.is_some() { DisconnectReason::Shutdown } else { reason };
+            server.remove_connection(&info.user_id, &conn_id, reason).await;
             info!("Connection closed: {}", conn_id);
         });
     }
 
-    /// 检查连接状态
-    async fn check_connections(connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>) {
-        let mut conns = connections.lock().await;
-        let now = chrono::Utc::now();
-        
-        conns.retain(|conn_id, info| {
-            if let Ok(last) = info.last_heartbeat.try_lock() {
-                if now.signed_duration_since(*last) > chrono::Duration::seconds(CONNECTION_TIMEOUT.as_secs() as i64) {
-                    warn!("Connection {} timed out", conn_id);
-                    false
-                } else {
-                    true
-                }
-            } else {
-                true
+    /// 优雅停机
+    ///
+    /// 拒绝新连接，向所有连接下发带重连建议的 `CLOSE`，
+    /// 在 `timeout` 内等待处理中的消息完成后关闭所有连接
+    pub async fn shutdown(&self, notice: CloseNotice, timeout: Duration) {
+        *self.draining.lock().await = Some(notice.clone());
+        let targets: Vec<ConnectionInfo> = self.connections.lock().await.values().cloned().collect();
+        info!("Shutting down, draining {} connections", targets.len());
+        let msg = ProtoMessage {
+            command: Command::Close as i32,
+            data: notice.encode_to_vec(),
+            ..Default::default()
+        };
+        join_all(targets.iter().map(|info| info.send(msg.clone()))).await;
+
+        if tokio::time::timeout(timeout, self.in_flight.wait_idle()).await.is_err() {
+            warn!("Shutdown timed out with {} messages in flight", self.in_flight.count());
+        }
+        let targets: Vec<ConnectionInfo> = self.connections.lock().await.values().cloned().collect();
+        for info in targets {
+            if let Err(e) = info.close().await {
+                warn!("Failed to close connection {}: {}", info.conn_id, e);
             }
-        });
+        }
+    }
+
+    /// 是否正在停机
+    pub async fn is_draining(&self) -> bool {
+        self.draining.lock().await.is_some()
+    }
+
+    async fn close_with_notice(conn: &dyn Connection, notice: CloseNotice) {
+        if let Err(e) = conn.send(ProtoMessage {
+            command: Command::Close as i32,
+            data: notice.encode_to_vec(),
+            ..Default::default()
+        }).await {
+            debug!("Failed to send close notice: {}", e);
+        }
+        if let Err(e) = conn.close().await {
+            debug!("Failed to close connection: {}", e);
+        }
     }
 
     /// 向用户发送消息
+    ///
+    /// 先投递本节点上的连接，再按会话存储转发到用户所在的其它节点
     pub async fn send_to_user(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
-        let user_conns = self.user_connections.lock().await;
-        if let Some(conn_ids) = user_conns.get(user_id) {
-            let conns = self.connections.lock().await;
-            for conn_id in conn_ids {
-                if let Some(info) = conns.get(conn_id) {
-                    if let Err(e) = info.send(msg.clone()).await {
-                        warn!("Failed to send message to {}: {}", conn_id, e);
-                    }
-                }
-            }
+        if !self.route_to_user(user_id, msg.clone()).await? {
+            self.store_offline(user_id, msg).await?;
         }
         Ok(())
     }
 
+    /// 可靠地向用户推送消息，返回服务端消息ID
+    ///
+    /// 消息由用户连接所在的节点等待 `CLIENT_ACK`，超时后按退避策略重试，
+    /// 最终未确认的消息通过 `ServerHandler::handle_undelivered` 上报
+    pub async fn send_reliable(&self, user_id: &str, mut msg: ProtoMessage) -> Result<String> {
+        if msg.server_msg_id.is_empty() {
+            msg.server_msg_id = DeliveryTracker::next_msg_id();
+        }
+        let msg_id = msg.server_msg_id.clone();
+        if !self.route_to_user(user_id, msg.clone()).await? {
+            // 用户不在线，直接上报
+            self.store_offline(user_id, msg.clone()).await?;
+            self.handler.handle_undelivered(user_id, &msg).await?;
+        }
+        Ok(msg_id)
+    }
+
+    /// 保存离线消息，未设置离线存储时丢弃
+    async fn store_offline(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        match &self.offline_store {
+            Some(store) => store.append(user_id, msg).await,
+            None => {
+                debug!("User {} is offline, message dropped", user_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// 投递到本节点并转发到其它节点，返回是否有节点接收了消息
+    async fn route_to_user(&self, user_id: &str, msg: ProtoMessage) -> Result<bool> {
+        let mut delivered = self.local().deliver(user_id, msg.clone()).await > 0;
+
+        let routes = self.session_store.lookup(user_id).await?;
+        let mut nodes: Vec<String> = routes.into_iter()
+            .map(|route| route.node_id)
+            .filter(|node_id| *node_id != self.node_id)
+            .collect();
+        nodes.sort();
+        nodes.dedup();
+        for node_id in nodes {
+            match self.router.forward(&node_id, user_id, msg.clone()).await {
+                Ok(()) => delivered = true,
+                Err(e) => warn!("Failed to forward message for {} to node {}: {}", user_id, node_id, e),
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// 只向本节点上的用户连接发送消息
+    pub async fn deliver_local(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        self.local().deliver_local(user_id, msg).await
+    }
+
 
     /// 向所有连接广播消息
     pub async fn broadcast(&self, msg: ProtoMessage) -> Result<()> {
-        let conns = self.connections.lock().await;
-        for (conn_id, info) in conns.iter() {
-            if let Err(e) = info.send(msg.clone()).await {
-                warn!("Failed to broadcast to {}: {}", conn_id, e);
+        let targets: Vec<ConnectionInfo> = self.connections.lock().await.values().cloned().collect();
+        let results = join_all(targets.iter().map(|info| info.push(msg.clone()))).await;
+        for (info, result) in targets.iter().zip(results) {
+            if let Err(e) = result {
+                warn!("Failed to broadcast to {}: {}", info.conn_id, e);
             }
         }
         Ok(())
     }
 
+    /// 在线状态
+    pub fn presence(&self) -> &PresenceHub {
+        &self.presence
+    }
+
+    /// 查询用户的在线状态
+    pub async fn get_presence(&self, user_id: &str) -> Presence {
+        self.presence.get(user_id).await
+    }
+
+    /// 房间表，仅包含本节点的连接
+    pub fn rooms(&self) -> &Rooms {
+        &self.rooms
+    }
+
+    /// 连接加入房间
+    pub async fn join_room(&self, room_id: &str, conn_id: &str) -> Result<()> {
+        self.rooms.join(room_id, conn_id).await
+    }
+
+    /// 连接离开房间
+    pub async fn leave_room(&self, room_id: &str, conn_id: &str) -> Result<()> {
+        self.rooms.leave(room_id, conn_id).await
+    }
+
+    /// 房间内的用户ID
+    pub async fn room_members(&self, room_id: &str) -> Vec<String> {
+        self.rooms.members(room_id).await
+    }
+
+    /// 向房间发送消息，`exclude_user` 为发送者时不回发给发送者，返回成功发送的连接数
+    pub async fn send_to_room(&self, room_id: &str, msg: ProtoMessage, exclude_user: Option<&str>) -> Result<usize> {
+        self.rooms.send(room_id, msg, exclude_user).await
+    }
+
     /// 获取连接信息
     pub async fn get_connection_info(&self, conn_id: &str) -> Option<ConnectionInfo> {
         let conns = self.connections.lock().await;
@@ -485,6 +1132,33 @@ where
             .unwrap_or_default()
     }
 
+    /// 获取本节点的所有连接，按建立时间排序
+    pub async fn get_connections(&self) -> Vec<ConnectionInfo> {
+        let mut conns: Vec<ConnectionInfo> = self.connections.lock().await.values().cloned().collect();
+        conns.sort_by_key(|info| info.connected_at);
+        conns
+    }
+
+    /// 查询会话存储中用户在所有节点上的会话
+    pub async fn lookup_sessions(&self, user_id: &str) -> Result<Vec<SessionRoute>> {
+        self.session_store.lookup(user_id).await
+    }
+
+    /// 踢下线用户在本节点的连接，`conn_id` 为 None 时踢掉所有连接，返回被踢的连接数
+    ///
+    /// 其他节点上的连接不受影响，可通过 `lookup_sessions` 找到所在节点后在该节点调用
+    pub async fn kick(&self, user_id: &str, conn_id: Option<&str>, reason: &str) -> usize {
+        let targets: Vec<String> = self.get_user_connections(user_id).await
+            .into_iter()
+            .map(|info| info.conn_id)
+            .filter(|id| conn_id.is_none_or(|target| target == id))
+            .collect();
+        for id in &targets {
+            self.kick_connection(user_id, id, reason).await;
+        }
+        targets.len()
+    }
+
     pub fn get_handler_mut(&mut self) -> &mut ServerMessageHandler<S, A, Y> {
         Arc::get_mut(&mut self.handler).unwrap()
     }
@@ -505,6 +1179,22 @@ where
     }
 }
 
+/// 提取 panic 信息
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// 执行处理器代码，panic 时返回 panic 信息，不影响连接任务
+async fn catch_panic<T>(fut: impl Future<Output = T>) -> std::result::Result<T, String> {
+    AssertUnwindSafe(fut).catch_unwind().await.map_err(|panic| panic_message(panic.as_ref()))
+}
+
 // 新增一个辅助结构体来处理生命周期问题
 struct ServerHandle<S, A, Y>
 where
@@ -514,6 +1204,18 @@ where
 {
     handler: Arc<ServerMessageHandler<S, A, Y>>,
     connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>,
+    user_connections: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    session_store: Arc<dyn SessionStore>,
+    tracker: DeliveryTracker,
+    offline_store: Option<Arc<dyn OfflineStore>>,
+    rooms: Rooms,
+    presence: PresenceHub,
+    rate_limiter: Option<RateLimiter>,
+    resume_tokens: Option<ResumeTokens>,
+    in_flight: InFlight,
+    draining: Arc<Mutex<Option<CloseNotice>>>,
+    events: broadcast::Sender<ConnectionEvent>,
+    heartbeat: HeartbeatPolicy,
 }
 
 impl<S, A, Y> ServerHandle<S, A, Y>
@@ -522,10 +1224,122 @@ where
     A: AuthHandler + Send + Sync + 'static,
     Y: SystemHandler + Send + Sync + 'static,
 {
+    /// 移除连接并清理用户映射和会话路由，连接已被移除时不重复通知
+    async fn remove_connection(&self, user_id: &str, conn_id: &str, reason: DisconnectReason) {
+        let Some(info) = self.connections.lock().await.remove(conn_id) else {
+            return;
+        };
+        metrics::connection_closed(&info.protocol, info.platform);
+        if let (Some(tokens), Some(token)) = (&self.resume_tokens, &info.resume_token) {
+            // 保存房间、订阅等状态，恢复会话时还原
+            tokens.detach(token, SessionState {
+                rooms: self.rooms.rooms_of(conn_id).await,
+                subscriptions: self.presence.subscriptions_of(user_id).await,
+                background: info.is_background().await,
+            }).await;
+        }
+        self.rooms.leave_all(conn_id).await;
+        let user_offline = {
+            let mut user_conns = self.user_connections.lock().await;
+            if let Some(conn_ids) = user_conns.get_mut(user_id) {
+                conn_ids.retain(|id| id != conn_id);
+                if conn_ids.is_empty() {
+                    user_conns.remove(user_id);
+                }
+            }
+            !user_conns.contains_key(user_id)
+        };
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.remove(conn_id, user_id, user_offline).await;
+        }
+        if let Err(e) = self.session_store.unregister(user_id, conn_id).await {
+            error!("Failed to unregister session for {}: {}", user_id, e);
+        }
+        self.presence.refresh(user_id).await;
+        debug!("Connection {} of user {} disconnected: {:?}", conn_id, user_id, reason);
+        if let Err(panic) = catch_panic(self.handler.on_disconnected(&info, reason)).await {
+            error!("on_disconnected panicked for {}: {}", conn_id, panic);
+        }
+        let _ = self.events.send(ConnectionEvent::Disconnected { info, reason });
+    }
+
+    /// 检查连接状态，按心跳策略移除超时的连接
+    async fn check_connections(&self) {
+        let now = chrono::Utc::now();
+        let conns: Vec<ConnectionInfo> = self.connections.lock().await.values().cloned().collect();
+        for info in conns {
+            let timeout = self.heartbeat.timeout(info.platform, info.is_background().await);
+            let timeout = chrono::Duration::from_std(timeout).unwrap_or(chrono::Duration::MAX);
+            let expired = match info.last_heartbeat.try_lock() {
+                Ok(last) => now.signed_duration_since(*last) > timeout,
+                Err(_) => false,
+            };
+            if !expired {
+                continue;
+            }
+            warn!("Connection {} timed out", info.conn_id);
+            metrics::heartbeat_timeout(info.platform);
+            // 先移除再关闭，断开原因不会被接收任务覆盖
+            self.remove_connection(&info.user_id, &info.conn_id, DisconnectReason::Timeout).await;
+            if let Err(e) = info.close().await {
+                debug!("Failed to close timed out connection {}: {}", info.conn_id, e);
+            }
+        }
+    }
+
+    /// 处理在线状态订阅
+    async fn subscribe_presence(&self, user_id: &str, data: &[u8]) -> Response {
+        let req = match PresenceSubReq::decode(data) {
+            Ok(req) => req,
+            Err(e) => return Response {
+                code: ResCode::DecodeError as i32,
+                message: e.to_string(),
+                data: Vec::new(),
+            },
+        };
+        let presences = if req.unsubscribe {
+            self.presence.unsubscribe(user_id, &req.user_ids).await;
+            Vec::new()
+        } else {
+            self.presence.subscribe(user_id, &req.user_ids).await
+        };
+        Response {
+            code: ResCode::Success as i32,
+            message: String::new(),
+            data: PresenceList { presences }.encode_to_vec(),
+        }
+    }
+
+    /// 处理离线消息拉取，会话拉取或未设置离线存储时返回 None 交给业务处理器
+    async fn pull_offline(&self, user_id: &str, data: &[u8]) -> Option<Response> {
+        let store = self.offline_store.as_ref()?;
+        let req = PullReq::decode(data).ok()?;
+        if !req.conversation_id.is_empty() {
+            return None;
+        }
+        let limit = if req.limit == 0 { DEFAULT_PULL_LIMIT } else { (req.limit as usize).min(MAX_PULL_LIMIT) };
+        Some(match store.fetch(user_id, req.cursor, limit).await {
+            Ok(page) => Response {
+                code: ResCode::Success as i32,
+                message: String::new(),
+                data: page.encode_to_vec(),
+            },
+            Err(e) => {
+                error!("Failed to pull offline messages for {}: {}", user_id, e);
+                Response {
+                    code: e.code() as i32,
+                    message: e.to_string(),
+                    data: Vec::new(),
+                }
+            }
+        })
+    }
+
     async fn build_context(&self, builder: AppContextBuilder, conn_id: String, client_msg_id: String) -> Option<AppContext> {
         match builder
             .with_conn_id(conn_id)
             .with_client_msg_id(client_msg_id)
+            .with_rooms(Arc::new(self.rooms.clone()))
             .build() 
         {
             Ok(ctx) => Some(ctx),
@@ -563,6 +1377,74 @@ where
     }
 }
 
+/// 本节点连接表，实现 `LocalDelivery`
+#[derive(Clone)]
+struct LocalConnections {
+    connections: Arc<Mutex<HashMap<String, ConnectionInfo>>>,
+    user_connections: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    tracker: DeliveryTracker,
+}
+
+impl LocalConnections {
+    /// 发送给用户在本节点的所有连接，返回成功发送的连接数
+    async fn send(&self, user_id: &str, msg: &ProtoMessage) -> usize {
+        let mut sent = 0;
+        let user_conns = self.user_connections.lock().await;
+        if let Some(conn_ids) = user_conns.get(user_id) {
+            let conns = self.connections.lock().await;
+            for conn_id in conn_ids {
+                if let Some(info) = conns.get(conn_id) {
+                    match info.push(msg.clone()).await {
+                        Ok(()) => sent += 1,
+                        Err(e) => warn!("Failed to send message to {}: {}", conn_id, e),
+                    }
+                }
+            }
+        }
+        sent
+    }
+
+    /// 发送消息，带服务端消息ID的消息会等待 ACK 并重试
+    async fn deliver(&self, user_id: &str, msg: ProtoMessage) -> usize {
+        let sent = self.send(user_id, &msg).await;
+        if sent > 0 && !msg.server_msg_id.is_empty() {
+            self.track(user_id.to_string(), msg).await;
+        }
+        sent
+    }
+
+    /// 启动 ACK 等待与重试任务
+    async fn track(&self, user_id: String, msg: ProtoMessage) {
+        let mut acked = self.tracker.register(&user_id, &msg.server_msg_id).await;
+        let local = self.clone();
+        tokio::spawn(async move {
+            let config = local.tracker.config().clone();
+            let mut attempt = 0;
+            loop {
+                tokio::select! {
+                    _ = &mut acked => return,
+                    _ = tokio::time::sleep(config.timeout_for(attempt)) => {}
+                }
+                if attempt >= config.max_retries {
+                    local.tracker.give_up(&user_id, msg).await;
+                    return;
+                }
+                attempt += 1;
+                debug!("Redeliver message {} to {}, attempt {}", msg.server_msg_id, user_id, attempt);
+                local.send(&user_id, &msg).await;
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl LocalDelivery for LocalConnections {
+    async fn deliver_local(&self, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        self.deliver(user_id, msg).await;
+        Ok(())
+    }
+}
+
 impl Default for Server<DefServerHandler, DefAuthHandler, DefSystemHandler> {
     fn default() -> Self {
         Self::new(ServerMessageHandler::<DefServerHandler, DefAuthHandler, DefSystemHandler>::default())
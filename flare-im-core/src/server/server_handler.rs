@@ -3,7 +3,7 @@ use flare_core::error::{FlareErr, Result};
 use crate::server::handlers::CommandHandler;
 use async_trait::async_trait;
 use log::debug;
-use flare_core::flare_net::net::{Command, ResCode, Response};
+use flare_core::flare_net::net::{Command, Message as ProtoMessage, ResCode, Response};
 
 /// 服务端处理器
 #[async_trait]
@@ -12,6 +12,10 @@ pub trait ServerHandler: Send + Sync {
     async fn handle_send_message(&self, ctx:  &AppContext) -> Result<Response>;
 
     /// 处理拉取消息
+    ///
+    /// 带 `conversation_id` 的 `PullReq` 是客户端发现会话序列号缺口后的补拉请求，
+    /// 内置离线存储不处理会话区间，由这里返回 `PullResp` 编码的 `[from_seq, to_seq]` 内的消息，
+    /// `has_more` 为 true 时客户端从最后一条消息之后继续补拉
     async fn handle_pull_message(&self, ctx:  &AppContext) -> Result<Response>;
 
     /// 处理数据请求
@@ -19,6 +23,12 @@ pub trait ServerHandler: Send + Sync {
 
     /// 处理消息ack
     async fn handle_ack(&self, ctx:  &AppContext) -> Result<Response>;
+
+    /// 可靠推送重试后仍未收到 ack
+    async fn handle_undelivered(&self, user_id: &str, msg: &ProtoMessage) -> Result<()> {
+        debug!("消息投递失败 - user_id: {}, server_msg_id: {}", user_id, msg.server_msg_id);
+        Ok(())
+    }
 }
 
 /// 服务端命令处理器
@@ -48,6 +58,10 @@ impl<T: ServerHandler + Send + Sync> ServerHandler for ServerCommandHandler<T> {
     async fn handle_ack(&self, ctx:  &AppContext) -> Result<Response> {
         self.0.handle_ack(ctx).await
     }
+
+    async fn handle_undelivered(&self, user_id: &str, msg: &ProtoMessage) -> Result<()> {
+        self.0.handle_undelivered(user_id, msg).await
+    }
 }
 
 #[async_trait]
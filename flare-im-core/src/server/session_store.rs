@@ -0,0 +1,206 @@
+use crate::server::auth_handler::AuthHandler;
+use crate::server::server::Server;
+use crate::server::server_handler::ServerHandler;
+use crate::server::sys_handler::SystemHandler;
+use async_trait::async_trait;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::{Message as ProtoMessage, Platform};
+use log::debug;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// 会话路由：用户的某个连接位于哪个网关节点
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionRoute {
+    pub node_id: String,
+    pub conn_id: String,
+    pub platform: Platform,
+}
+
+/// 会话存储，记录 user_id -> (node_id, conn_id)
+///
+/// 多个网关节点共享同一个存储时，`Server::send_to_user` 可以找到用户所在的节点
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// 注册会话
+    async fn register(&self, user_id: &str, route: SessionRoute) -> Result<()>;
+    /// 注销会话
+    async fn unregister(&self, user_id: &str, conn_id: &str) -> Result<()>;
+    /// 查询用户的所有会话
+    async fn lookup(&self, user_id: &str) -> Result<Vec<SessionRoute>>;
+}
+
+/// 基于内存的会话存储，克隆后共享同一份数据
+#[derive(Clone, Default)]
+pub struct MemorySessionStore {
+    sessions: Arc<Mutex<HashMap<String, Vec<SessionRoute>>>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn register(&self, user_id: &str, route: SessionRoute) -> Result<()> {
+        let mut sessions = self.sessions.lock().await;
+        let routes = sessions.entry(user_id.to_string()).or_default();
+        routes.retain(|r| r.conn_id != route.conn_id);
+        routes.push(route);
+        Ok(())
+    }
+
+    async fn unregister(&self, user_id: &str, conn_id: &str) -> Result<()> {
+        let mut sessions = self.sessions.lock().await;
+        if let Some(routes) = sessions.get_mut(user_id) {
+            routes.retain(|r| r.conn_id != conn_id);
+            if routes.is_empty() {
+                sessions.remove(user_id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn lookup(&self, user_id: &str) -> Result<Vec<SessionRoute>> {
+        let sessions = self.sessions.lock().await;
+        Ok(sessions.get(user_id).cloned().unwrap_or_default())
+    }
+}
+
+/// 本节点投递：把消息发给本节点上该用户的所有连接
+#[async_trait]
+pub trait LocalDelivery: Send + Sync {
+    async fn deliver_local(&self, user_id: &str, msg: ProtoMessage) -> Result<()>;
+}
+
+/// 节点路由：把消息转发给其它网关节点
+#[async_trait]
+pub trait NodeRouter: Send + Sync {
+    async fn forward(&self, node_id: &str, user_id: &str, msg: ProtoMessage) -> Result<()>;
+}
+
+/// 默认的单节点路由，没有可转发的节点
+#[derive(Default)]
+pub struct LocalRouter;
+
+impl LocalRouter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl NodeRouter for LocalRouter {
+    async fn forward(&self, node_id: &str, user_id: &str, _msg: ProtoMessage) -> Result<()> {
+        debug!("No router configured, drop message for user {} on node {}", user_id, node_id);
+        Err(FlareErr::not_found_service(format!("node {}", node_id)))
+    }
+}
+
+/// 进程内的多节点集群，用于测试跨网关投递
+///
+/// 所有加入的节点共享同一个 `MemorySessionStore`，转发时直接调用目标节点的 `LocalDelivery`
+#[derive(Clone, Default)]
+pub struct MemoryCluster {
+    store: MemorySessionStore,
+    nodes: Arc<Mutex<HashMap<String, Arc<dyn LocalDelivery>>>>,
+}
+
+impl MemoryCluster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 共享的会话存储
+    pub fn store(&self) -> MemorySessionStore {
+        self.store.clone()
+    }
+
+    /// 加入节点
+    pub async fn join(&self, node_id: &str, delivery: Arc<dyn LocalDelivery>) {
+        self.nodes.lock().await.insert(node_id.to_string(), delivery);
+    }
+
+    /// 移除节点
+    pub async fn leave(&self, node_id: &str) {
+        self.nodes.lock().await.remove(node_id);
+    }
+
+    /// 将服务器作为一个节点加入集群
+    pub async fn attach<S, A, Y>(&self, node_id: &str, server: Server<S, A, Y>) -> Server<S, A, Y>
+    where
+        S: ServerHandler + Send + Sync + 'static,
+        A: AuthHandler + Send + Sync + 'static,
+        Y: SystemHandler + Send + Sync + 'static,
+    {
+        let server = server
+            .with_node_id(node_id)
+            .with_session_store(self.store())
+            .with_router(self.clone());
+        self.join(node_id, server.local_delivery()).await;
+        server
+    }
+}
+
+#[async_trait]
+impl NodeRouter for MemoryCluster {
+    async fn forward(&self, node_id: &str, user_id: &str, msg: ProtoMessage) -> Result<()> {
+        let delivery = self.nodes.lock().await.get(node_id).cloned();
+        match delivery {
+            Some(delivery) => delivery.deliver_local(user_id, msg).await,
+            None => Err(FlareErr::not_found_service(format!("node {}", node_id))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock::{self, DEFAULT_USER};
+    use flare_core::flare_net::net::Command;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_memory_session_store() {
+        let store = MemorySessionStore::new();
+        let route = SessionRoute {
+            node_id: "node-1".into(),
+            conn_id: "conn-1".into(),
+            platform: Platform::Web,
+        };
+        store.register("u1", route.clone()).await.unwrap();
+        store.register("u1", route.clone()).await.unwrap();
+        assert_eq!(store.lookup("u1").await.unwrap(), vec![route]);
+
+        store.unregister("u1", "conn-1").await.unwrap();
+        assert!(store.lookup("u1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cross_node_delivery() {
+        let cluster = MemoryCluster::new();
+        let node1 = Arc::new(cluster.attach("node-1", Server::default()).await);
+        let node2 = cluster.attach("node-2", Server::default()).await;
+
+        // 用户连接到 node-1
+        let mut peer = mock::login_default(node1).await;
+        assert!(mock::eventually(|| async { !cluster.store().lookup(DEFAULT_USER).await.unwrap().is_empty() }).await);
+        let routes = cluster.store().lookup(DEFAULT_USER).await.unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].node_id, "node-1");
+
+        // 从 node-2 推送，消息经集群转发到 node-1
+        node2.send_to_user(DEFAULT_USER, ProtoMessage {
+            command: Command::ServerPushMsg as i32,
+            data: b"hello".to_vec(),
+            ..Default::default()
+        }).await.unwrap();
+        let pushed = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(pushed.command, Command::ServerPushMsg as i32);
+        assert_eq!(pushed.data, b"hello".to_vec());
+        assert_eq!(pushed.seq, 1);
+    }
+}
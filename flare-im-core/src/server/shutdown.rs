@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// 正在处理中的消息计数，停机时等待计数归零
+#[derive(Clone, Default)]
+pub struct InFlight {
+    inner: Arc<(AtomicUsize, Notify)>,
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始处理一条消息，返回的守卫释放时计数减一
+    pub fn enter(&self) -> InFlightGuard {
+        self.inner.0.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard { inner: self.inner.clone() }
+    }
+
+    pub fn count(&self) -> usize {
+        self.inner.0.load(Ordering::SeqCst)
+    }
+
+    /// 等待所有处理中的消息完成
+    pub async fn wait_idle(&self) {
+        loop {
+            let notified = self.inner.1.notified();
+            tokio::pin!(notified);
+            // 先注册再检查计数，避免错过通知
+            notified.as_mut().enable();
+            if self.count() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+pub struct InFlightGuard {
+    inner: Arc<(AtomicUsize, Notify)>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.inner.0.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.inner.1.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::mock::{self, DEFAULT_USER};
+    use crate::server::router::Router;
+    use crate::server::server::Server;
+    use flare_core::context::AppContext;
+    use flare_core::flare_net::net::{CloseNotice, Command, LoginReq, LoginResp, Message as ProtoMessage, ResCode, Response};
+    use prost::Message;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight() {
+        let router = Router::new().route("slow", |_ctx: AppContext, _req: LoginReq| async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(LoginResp::default())
+        });
+        let server = Arc::new(Server::default().with_routes(router));
+        let mut peer = mock::login_default(server.clone()).await;
+        peer.tx.send(ProtoMessage {
+            command: Command::ClientRequest as i32,
+            route: "slow".into(),
+            ..Default::default()
+        }).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let notice = CloseNotice { reason: "deploy".into(), reconnect: true, retry_after_ms: 500 };
+        server.shutdown(notice.clone(), Duration::from_secs(1)).await;
+        assert!(server.is_draining().await);
+
+        let close = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(close.command, Command::Close as i32);
+        assert_eq!(CloseNotice::decode(&close.data[..]).unwrap(), notice);
+        // 关闭前等待处理中的请求返回
+        let reply = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(Response::decode(&reply.data[..]).unwrap().code, ResCode::Success as i32);
+
+        assert!(mock::eventually(|| async { server.get_user_connections(DEFAULT_USER).await.is_empty() }).await);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_during_auth() {
+        let server = Arc::new(Server::default());
+        let (conn, mut peer) = mock::pair();
+        let handle = server.clone();
+        let task = tokio::spawn(async move { handle.add_connection(Box::new(conn)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // 停机开始时连接还在等待登录，认证完成后同样收到关闭通知
+        let notice = CloseNotice { reason: "deploy".into(), reconnect: true, retry_after_ms: 500 };
+        server.shutdown(notice.clone(), Duration::from_secs(1)).await;
+        peer.tx.send(ProtoMessage {
+            command: Command::Login as i32,
+            data: mock::login_req().encode_to_vec(),
+            ..Default::default()
+        }).unwrap();
+        let login = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(login.command, Command::ServerResponse as i32);
+        let close = peer.recv_timeout(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(CloseNotice::decode(&close.data[..]).unwrap(), notice);
+
+        task.await.unwrap();
+        assert!(server.get_user_connections(DEFAULT_USER).await.is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 use flare_core::context::AppContext;
 use flare_core::error::{FlareErr, Result};
 use crate::server::handlers::CommandHandler;
+use crate::server::lifecycle::DisconnectReason;
 use crate::server::server::ConnectionInfo;
 use async_trait::async_trait;
 use log::debug;
@@ -16,6 +17,12 @@ pub trait SystemHandler: Send + Sync {
     async fn handle_set_language(&self, ctx:  &AppContext, language: String) -> Result<Response>;
     /// 关闭
     async fn handle_close(&self, ctx:  &AppContext) -> Result<Response>;
+    /// 建立传输连接，尚未认证
+    async fn on_connected(&self, _conn_id: &str, _remote_addr: &str) {}
+    /// 认证完成并注册到用户连接表
+    async fn on_authenticated(&self, _conn: &ConnectionInfo) {}
+    /// 连接已移除，每个已认证的连接只回调一次
+    async fn on_disconnected(&self, _conn: &ConnectionInfo, _reason: DisconnectReason) {}
 }
 
 /// 系统命令处理器
@@ -44,6 +51,18 @@ impl<T: SystemHandler + Send + Sync> SystemHandler for SystemCommandHandler<T> {
     async fn handle_close(&self, ctx:  &AppContext) -> Result<Response> {
         self.0.handle_close(ctx).await
     }
+
+    async fn on_connected(&self, conn_id: &str, remote_addr: &str) {
+        self.0.on_connected(conn_id, remote_addr).await
+    }
+
+    async fn on_authenticated(&self, conn: &ConnectionInfo) {
+        self.0.on_authenticated(conn).await
+    }
+
+    async fn on_disconnected(&self, conn: &ConnectionInfo, reason: DisconnectReason) {
+        self.0.on_disconnected(conn, reason).await
+    }
 }
 
 #[async_trait]
@@ -2,7 +2,6 @@ use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
-use tokio_tungstenite::connect_async;
 use quinn::Endpoint;
 use crate::client::client::{Client, ClientState};
 use crate::client::config::ClientConfig;
@@ -10,8 +9,10 @@ use crate::client::handlers::ClientMessageHandler;
 use crate::client::sys_handler::ClientSystemHandler;
 use crate::client::message_handler::MessageHandler;
 use flare_core::error::{Result, FlareErr};
-use crate::connections::{Connection, WsConnection, QuicConnection};
-use log::{info, debug, error};
+use crate::connections::{Connection, QuicConnection, TcpConnection};
+use crate::connections::quic::QuicStreamOptions;
+use crate::connections::ws::{connect_ws, WsTlsConfig};
+use log::{info, debug, error, warn};
 use std::net::SocketAddr;
 use std::pin::Pin;
 use flare_core::flare_net::net::{Message, Platform, Response};
@@ -22,6 +23,8 @@ pub enum Protocol {
     Auto,
     WebSocket,
     Quic,
+    /// 原始 TCP，仅在显式指定时使用，不参与 Auto 竞速
+    Tcp,
 }
 /// 连接信息结构体
 #[derive(Debug, Clone)]
@@ -46,6 +49,10 @@ where
     quic_server_name: String,
     quic_cert_path: String,
     quic_is_test: bool,
+    quic_options: QuicStreamOptions,
+    quic_endpoint: Arc<Mutex<Option<Endpoint>>>,
+    ws_tls: WsTlsConfig,
+    tcp_addr: String,
     config: ClientConfig,
     handler: Arc<ClientMessageHandler<S, M>>,
     state: Arc<Mutex<ClientState>>,
@@ -81,6 +88,10 @@ where
             quic_server_name,
             quic_cert_path,
             quic_is_test,
+            quic_options: QuicStreamOptions::default(),
+            quic_endpoint: Arc::new(Mutex::new(None)),
+            ws_tls: WsTlsConfig::default(),
+            tcp_addr: String::new(),
             config,
             handler: Arc::new(handler),
             state: Arc::new(Mutex::new(ClientState::Disconnected)),
@@ -204,6 +215,11 @@ where
         self.client.send_wait_timeout(msg, timeout).await
     }
 
+    /// 切换前后台，心跳间隔随之切换
+    pub async fn set_background(&self, background: bool) -> Result<()> {
+        self.client.set_background(background).await
+    }
+
     /// 获取当前状态
     pub async fn get_state(&self) -> ClientState {
         self.state.lock().await.clone()
@@ -261,38 +277,89 @@ where
         }
     }
 
-    // 连接 QUIC
+    // 连接 QUIC，复用同一个 endpoint 以保留会话票据，重连时尝试 0-RTT
     async fn connect_quic(&self) -> Result<Box<dyn Connection>> {
         let addr = self.quic_addr.parse::<SocketAddr>()
             .map_err(|e| FlareErr::ConnectionError(format!("Invalid QUIC address: {}", e)))?;
 
-        let mut endpoint = Endpoint::client("0.0.0.0:0".parse().unwrap())
-            .map_err(|e| FlareErr::ConnectionError(format!("Failed to create QUIC endpoint: {}", e)))?;
-        let client_config = create_client_config(self.quic_cert_path.as_str(),self.quic_is_test)
-            .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
-        endpoint.set_default_client_config(client_config);
-
+        let endpoint = self.quic_endpoint().await?;
         let connecting = endpoint.connect(addr, &self.quic_server_name)
             .map_err(|e| FlareErr::ConnectionError(format!("Failed to connect QUIC: {}", e)))?;
 
-        let connection = connecting.await
-            .map_err(|e| FlareErr::ConnectionError(format!("QUIC connection failed: {}", e)))?;
+        let connection = match connecting.into_0rtt() {
+            Ok((connection, accepted)) => {
+                debug!("Sending QUIC 0-RTT data to {}", addr);
+                let conn = connection.clone();
+                tokio::spawn(async move {
+                    // 0-RTT 被拒绝时已发送的数据丢失，关闭连接交给重连处理
+                    if !accepted.await {
+                        warn!("QUIC 0-RTT rejected by server");
+                        conn.close(0u32.into(), b"0-RTT rejected");
+                    }
+                });
+                connection
+            }
+            Err(connecting) => connecting.await
+                .map_err(|e| FlareErr::ConnectionError(format!("QUIC connection failed: {}", e)))?,
+        };
 
-        let quic_conn = QuicConnection::connect(connection, addr.to_string()).await?;
+        let quic_conn = QuicConnection::connect(connection, addr.to_string()).await?
+            .with_options(self.quic_options.clone());
         Ok(Box::new(quic_conn))
     }
 
-    // 连接 WebSocket
-    async fn connect_websocket(&self) -> Result<Box<dyn Connection>> {
-        let url = url::Url::parse(&self.ws_url)
-            .map_err(|e| FlareErr::ConnectionError(format!("Invalid WebSocket URL: {}", e)))?;
+    async fn quic_endpoint(&self) -> Result<Endpoint> {
+        let mut endpoint = self.quic_endpoint.lock().await;
+        if let Some(endpoint) = endpoint.as_ref() {
+            return Ok(endpoint.clone());
+        }
+        let mut created = Endpoint::client("0.0.0.0:0".parse().unwrap())
+            .map_err(|e| FlareErr::ConnectionError(format!("Failed to create QUIC endpoint: {}", e)))?;
+        let client_config = create_client_config(self.quic_cert_path.as_str(),self.quic_is_test)
+            .map_err(|e| FlareErr::ConnectionError(e.to_string()))?;
+        created.set_default_client_config(client_config);
+        *endpoint = Some(created.clone());
+        Ok(created)
+    }
+
+    /// 网络切换后换用新的本地 UDP 端口，QUIC 连接迁移到新地址而无需重连
+    pub async fn migrate(&self) -> Result<()> {
+        if let Some(endpoint) = self.quic_endpoint.lock().await.as_ref() {
+            let socket = std::net::UdpSocket::bind("0.0.0.0:0")
+                .map_err(|e| FlareErr::ConnectionError(format!("Failed to bind UDP socket: {}", e)))?;
+            endpoint.rebind(socket)
+                .map_err(|e| FlareErr::ConnectionError(format!("Failed to rebind QUIC endpoint: {}", e)))?;
+            info!("QUIC endpoint migrated to {:?}", endpoint.local_addr().ok());
+        }
+        Ok(())
+    }
+
+    /// 设置 QUIC 流的使用方式
+    pub fn with_quic_options(mut self, options: QuicStreamOptions) -> Self {
+        self.quic_options = options;
+        self
+    }
+
+    /// 设置 wss 证书校验配置
+    pub fn with_ws_tls(mut self, tls: WsTlsConfig) -> Self {
+        self.ws_tls = tls;
+        self
+    }
+
+    /// 设置 TCP 服务端地址
+    pub fn with_tcp_addr(mut self, addr: impl Into<String>) -> Self {
+        self.tcp_addr = addr.into();
+        self
+    }
 
-        // 将 url::Url 转换为字符串
-        let (ws_stream, _) = connect_async(url.as_str())
-            .await
-            .map_err(|e| FlareErr::ConnectionError(format!("WebSocket connection failed: {}", e)))?;
+    // 连接 TCP
+    async fn connect_tcp(&self) -> Result<Box<dyn Connection>> {
+        Ok(Box::new(TcpConnection::connect(self.tcp_addr.as_str()).await?))
+    }
 
-        Ok(Box::new(WsConnection::new(ws_stream, "websocket".to_string())))
+    // 连接 WebSocket，wss 地址按 ws_tls 校验证书
+    async fn connect_websocket(&self) -> Result<Box<dyn Connection>> {
+        connect_ws(&self.ws_url, &self.ws_tls).await
     }
 
     // 实际的连接尝试逻辑
@@ -325,6 +392,18 @@ where
                     }
                 }
             }
+            Protocol::Tcp => {
+                info!("Using TCP protocol");
+                match self.connect_tcp().await {
+                    Ok(conn) => {
+                        self.update_client_connector(conn).await
+                    }
+                    Err(e) => {
+                        error!("TCP connection failed: {}", e);
+                        Err(e)
+                    }
+                }
+            }
         }
     }
 
@@ -484,6 +563,9 @@ where
     quic_server_name: Option<String>,
     quic_cert_path: Option<String>,
     quic_is_test: bool,
+    quic_options: QuicStreamOptions,
+    ws_tls: WsTlsConfig,
+    tcp_addr: Option<String>,
     client_config: Option<ClientConfig>,
     handler: Option<ClientMessageHandler<S, M>>,
     protocol: Protocol,
@@ -501,6 +583,9 @@ where
             quic_server_name: None,
             quic_cert_path: None,
             quic_is_test: false,
+            quic_options: QuicStreamOptions::default(),
+            ws_tls: WsTlsConfig::default(),
+            tcp_addr: None,
             client_config: None,
             handler: None,
             protocol: Protocol::Auto,
@@ -532,6 +617,23 @@ where
         self
     }
 
+    /// 设置 QUIC 大消息和请求使用独立流的方式
+    pub fn quic_stream_options(mut self, options: QuicStreamOptions) -> Self {
+        self.quic_options = options;
+        self
+    }
+
+    /// 设置 wss 的根证书和校验方式
+    pub fn ws_tls(mut self, tls: WsTlsConfig) -> Self {
+        self.ws_tls = tls;
+        self
+    }
+
+    pub fn tcp_addr(mut self, addr: impl Into<String>) -> Self {
+        self.tcp_addr = Some(addr.into());
+        self
+    }
+
     pub fn client_config(mut self, config: ClientConfig) -> Self {
         self.client_config = Some(config);
         self
@@ -557,6 +659,11 @@ where
         self
     }
 
+    pub fn use_tcp(mut self) -> Self {
+        self.protocol = Protocol::Tcp;
+        self
+    }
+
     pub fn build(self) -> Result<FlareClient<S, M>> {
         let handler = self.handler.ok_or_else(|| anyhow::anyhow!("Handler is required"))?;
         let client_config = self.client_config.unwrap_or_default();
@@ -574,6 +681,11 @@ where
                     return Err(anyhow::anyhow!("QUIC configuration is incomplete").into());
                 }
             }
+            Protocol::Tcp => {
+                if self.tcp_addr.is_none() {
+                    return Err(anyhow::anyhow!("TCP address is required").into());
+                }
+            }
         }
         
         Ok(FlareClient::new(
@@ -585,7 +697,9 @@ where
             client_config,
             handler,
             self.protocol,
-        ))
+        ).with_ws_tls(self.ws_tls)
+            .with_quic_options(self.quic_options)
+            .with_tcp_addr(self.tcp_addr.unwrap_or_default()))
     }
 }
 
@@ -9,4 +9,4 @@ pub use self::client::{FlareClient, FlareClientBuilder, ConnectionInfo, Protocol
 
 // 服务端导出
 #[cfg(feature = "server")]
-pub use self::server::{FlareServer, FlareServerBuilder};
\ No newline at end of file
+pub use self::server::{FlareServer, FlareServerBuilder, QuicListener, ShutdownHandle, WsListener};
\ No newline at end of file
@@ -1,17 +1,94 @@
 use std::sync::Arc;
 use std::net::SocketAddr;
 use std::format;
+use std::time::Duration;
+use futures::future::BoxFuture;
+use futures::stream::{FuturesUnordered, StreamExt};
 use tokio::net::TcpListener;
-use tokio_tungstenite::accept_async;
+use tokio::sync::watch;
 use crate::server::auth_handler::AuthHandler;
 use crate::server::handlers::ServerMessageHandler;
 use crate::server::server::Server;
 use flare_core::error::{Result, FlareErr};
-use crate::connections::{WsConnection, QuicConnection};
+use crate::connections::{QuicConnection, TcpConnection};
+use crate::connections::ws::{accept_ws, create_ws_acceptor, WsHandshakeConfig};
 use crate::server::server_handler::ServerHandler;
 use crate::server::sys_handler::SystemHandler;
 use log::{info, error};
+use flare_core::flare_net::net::CloseNotice;
 use crate::connections::quic_conf::create_server_config;
+#[cfg(feature = "admin")]
+use crate::server::admin::ServerAdmin;
+#[cfg(feature = "admin")]
+use flare_rpc_core::admin::GatewayAdminService;
+
+/// 默认停机等待时间
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// WebSocket 监听配置，设置证书后以 wss 提供服务
+#[derive(Debug, Clone)]
+pub struct WsListener {
+    pub addr: String,
+    pub cert_path: Option<String>,
+    pub key_path: Option<String>,
+    /// 升级请求的路径和 Origin 校验
+    pub handshake: WsHandshakeConfig,
+}
+
+impl WsListener {
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            cert_path: None,
+            key_path: None,
+            handshake: WsHandshakeConfig::default(),
+        }
+    }
+
+    /// 只在指定 HTTP 路径上接受升级，例如 `/ws`
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.handshake.path = Some(path.into());
+        self
+    }
+
+    /// 设置握手校验配置
+    pub fn with_handshake(mut self, handshake: WsHandshakeConfig) -> Self {
+        self.handshake = handshake;
+        self
+    }
+
+    /// 启用 TLS
+    pub fn with_tls(mut self, cert_path: impl Into<String>, key_path: impl Into<String>) -> Self {
+        self.cert_path = Some(cert_path.into());
+        self.key_path = Some(key_path.into());
+        self
+    }
+}
+
+/// QUIC 监听配置
+#[derive(Debug, Clone)]
+pub struct QuicListener {
+    pub addr: String,
+    pub server_name: String,
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+impl QuicListener {
+    pub fn new(
+        addr: impl Into<String>,
+        server_name: impl Into<String>,
+        cert_path: impl Into<String>,
+        key_path: impl Into<String>,
+    ) -> Self {
+        Self {
+            addr: addr.into(),
+            server_name: server_name.into(),
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        }
+    }
+}
 
 pub struct FlareServer<S, A, Y>
 where
@@ -20,11 +97,31 @@ where
     Y: SystemHandler + Send + Sync + 'static,
 {
     server: Arc<Server<S, A, Y>>,
-    ws_addr: String,
-    quic_addr: String,
-    quic_server_name: String,
-    quic_cert_path: String,
-    quic_key_path: String,
+    ws_listeners: Vec<WsListener>,
+    quic_listeners: Vec<QuicListener>,
+    tcp_addrs: Vec<String>,
+    #[cfg(feature = "admin")]
+    admin_addr: Option<String>,
+    #[cfg(feature = "admin")]
+    admin_token: Option<String>,
+    #[cfg(feature = "metrics")]
+    metrics_addr: Option<String>,
+    drain_timeout: Duration,
+    reconnect_after: Duration,
+    shutdown: Arc<watch::Sender<bool>>,
+}
+
+/// 停机句柄，可在其它任务中触发 `FlareServer::run` 退出
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    /// 触发优雅停机
+    pub fn shutdown(&self) {
+        self.tx.send_replace(true);
+    }
 }
 
 impl<S, A, Y> FlareServer<S, A, Y>
@@ -34,20 +131,27 @@ where
     Y: SystemHandler + Send + Sync + 'static,
 {
     pub fn new(
-        ws_addr: String, 
-        quic_addr: String, 
-        quic_cert_path: String, 
+        ws_addr: String,
+        quic_addr: String,
+        quic_cert_path: String,
         quic_key_path: String,
         quic_server_name: String,
         server: Server<S, A, Y>,
     ) -> Self {
         FlareServer {
             server: Arc::new(server),
-            ws_addr,
-            quic_addr,
-            quic_server_name,
-            quic_cert_path,
-            quic_key_path,
+            ws_listeners: vec![WsListener::new(ws_addr)],
+            quic_listeners: vec![QuicListener::new(quic_addr, quic_server_name, quic_cert_path, quic_key_path)],
+            tcp_addrs: Vec::new(),
+            #[cfg(feature = "admin")]
+            admin_addr: None,
+            #[cfg(feature = "admin")]
+            admin_token: None,
+            #[cfg(feature = "metrics")]
+            metrics_addr: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            reconnect_after: Duration::ZERO,
+            shutdown: Arc::new(watch::channel(false).0),
         }
     }
 
@@ -55,50 +159,117 @@ where
         FlareServerBuilder::new()
     }
 
-    /// 运行服务器
+    /// 停机句柄
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle { tx: self.shutdown.clone() }
+    }
+
+    /// 运行服务器，直到通过 `ShutdownHandle` 触发停机
+    ///
+    /// 各监听独立运行，单个监听失败只记录日志，全部监听退出时返回错误
     pub async fn run(&self) -> Result<()> {
-        // 启动 WebSocket 服务器
-        let ws_server = self.run_ws_server();
-        // 启动 QUIC 服务器
-        let quic_server = self.run_quic_server();
-
-        // 并发运行两个服务器
-        tokio::select! {
-            result = ws_server => {
-                if let Err(e) = result {
-                    error!("WebSocket server error: {}", e);
-                }
-            }
-            result = quic_server => {
-                if let Err(e) = result {
-                    error!("QUIC server error: {}", e);
+        let mut shutdown = self.shutdown.subscribe();
+
+        let mut listeners: FuturesUnordered<BoxFuture<'_, (String, Result<()>)>> = FuturesUnordered::new();
+        for listener in &self.ws_listeners {
+            listeners.push(Box::pin(async move {
+                (format!("WebSocket {}", listener.addr), self.run_ws_server(listener).await)
+            }));
+        }
+        for listener in &self.quic_listeners {
+            listeners.push(Box::pin(async move {
+                (format!("QUIC {}", listener.addr), self.run_quic_server(listener).await)
+            }));
+        }
+        for addr in &self.tcp_addrs {
+            listeners.push(Box::pin(async move {
+                (format!("TCP {}", addr), self.run_tcp_server(addr).await)
+            }));
+        }
+        #[cfg(feature = "admin")]
+        if let Some(addr) = &self.admin_addr {
+            listeners.push(Box::pin(async move {
+                (format!("Admin {}", addr), self.run_admin_server(addr).await)
+            }));
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(addr) = &self.metrics_addr {
+            listeners.push(Box::pin(async move {
+                (format!("Metrics {}", addr), run_metrics_server(addr).await)
+            }));
+        }
+
+        loop {
+            tokio::select! {
+                next = listeners.next() => match next {
+                    Some((name, Ok(()))) => info!("{} listener stopped", name),
+                    Some((name, Err(e))) => error!("{} listener error: {}", name, e),
+                    None => {
+                        return Err(FlareErr::ConnectionError("All listeners stopped".to_string()));
+                    }
+                },
+                _ = wait_shutdown(&mut shutdown) => {
+                    // 释放所有监听，不再接受新连接
+                    drop(listeners);
+                    info!("Shutdown requested, draining connections");
+                    self.server.shutdown(CloseNotice {
+                        reason: "server shutting down".into(),
+                        reconnect: true,
+                        retry_after_ms: self.reconnect_after.as_millis().min(u32::MAX as u128) as u32,
+                    }, self.drain_timeout).await;
+                    return Ok(());
                 }
             }
         }
+    }
 
-        Ok(())
+    /// 运行网关管理服务，停机时随监听一起释放
+    #[cfg(feature = "admin")]
+    async fn run_admin_server(&self, addr: &str) -> Result<()> {
+        let addr = addr.parse::<SocketAddr>()
+            .map_err(|e| FlareErr::ConnectionError(format!("Invalid admin address: {}", e)))?;
+        let mut service = GatewayAdminService::new(ServerAdmin::new(self.server.clone()));
+        match &self.admin_token {
+            Some(token) => service = service.with_token(token.clone()),
+            // 管理接口可以踢人和推送消息，不校验调用方时不能对外暴露
+            None if !addr.ip().is_loopback() => {
+                return Err(FlareErr::ConnectionError(format!("Admin address {} is not loopback, set an admin token", addr)));
+            }
+            None => {}
+        }
+        service.serve(addr, std::future::pending()).await
     }
 
     /// 运行 WebSocket 服务器
-    async fn run_ws_server(&self) -> Result<()> {
-        let ws_addr = self.ws_addr.parse::<SocketAddr>()
+    async fn run_ws_server(&self, config: &WsListener) -> Result<()> {
+        let ws_addr = config.addr.parse::<SocketAddr>()
             .map_err(|e| FlareErr::ConnectionError(format!("Invalid WebSocket address: {}", e)))?;
-        
+
+        let acceptor = match (&config.cert_path, &config.key_path) {
+            (Some(cert_path), Some(key_path)) => Some(Arc::new(create_ws_acceptor(cert_path, key_path)?)),
+            (None, None) => None,
+            _ => return Err(FlareErr::ConnectionError("WebSocket TLS requires both cert and key".to_string())),
+        };
+
         let listener = TcpListener::bind(&ws_addr).await
             .map_err(|e| FlareErr::ConnectionError(format!("Failed to bind WebSocket: {}", e)))?;
-        
-        info!("WebSocket server listening on {}", ws_addr);
-        
+
+        info!("WebSocket server listening on {}{}{}", ws_addr,
+            config.handshake.path.as_deref().unwrap_or(""),
+            if acceptor.is_some() { " (TLS)" } else { "" });
+
         let server = self.server.clone();
-        
+        let handshake = Arc::new(config.handshake.clone());
+
         loop {
             if let Ok((stream, addr)) = listener.accept().await {
                 let server = server.clone();
-                
+                let acceptor = acceptor.clone();
+                let handshake = handshake.clone();
+
                 tokio::spawn(async move {
-                    match accept_async(stream).await {
-                        Ok(ws_stream) => {
-                            let conn = Box::new(WsConnection::new(ws_stream, addr.to_string()));
+                    match accept_ws(stream, addr.to_string(), acceptor.as_deref(), &handshake).await {
+                        Ok(conn) => {
                             let _ = server.add_connection(conn).await;
                         }
                         Err(e) => error!("Failed to accept WebSocket connection: {}", e),
@@ -109,12 +280,12 @@ where
     }
 
     /// 运行 QUIC 服务器
-    async fn run_quic_server(&self) -> Result<()> {
-        let quic_addr = self.quic_addr.parse::<SocketAddr>()
+    async fn run_quic_server(&self, config: &QuicListener) -> Result<()> {
+        let quic_addr = config.addr.parse::<SocketAddr>()
             .map_err(|e| FlareErr::ConnectionError(format!("Invalid QUIC address: {}", e)))?;
         let server_config = create_server_config(
-            self.quic_cert_path.as_str(),
-            self.quic_key_path.as_str(),
+            config.cert_path.as_str(),
+            config.key_path.as_str(),
         )?;
 
         let endpoint = quinn::Endpoint::server(
@@ -122,20 +293,34 @@ where
             quic_addr,
         ).map_err(|e| FlareErr::ConnectionError(format!("Failed to create QUIC endpoint: {}", e)))?;
 
-        info!("QUIC server listening on {}", quic_addr);
-        
+        info!("QUIC server listening on {} ({})", quic_addr, config.server_name);
+
         let server = self.server.clone();
-        let server_name = Arc::new(self.quic_server_name.clone());
-        
-        while let Some(connecting) = endpoint.accept().await {
+
+        while let Some(incoming) = endpoint.accept().await {
             let server = server.clone();
-            let server_name = Arc::clone(&server_name);
-            
+
             tokio::spawn(async move {
-                match connecting.await {
-                    Ok(new_conn) => {
-                        match QuicConnection::new(new_conn, (*server_name).clone()).await {
-                            Ok(conn) => {
+                // 服务端接受 0-RTT，恢复会话的客户端无需等待握手完成即可登录，其他登录在握手确认后才认证
+                let connecting = match incoming.accept() {
+                    Ok(connecting) => connecting,
+                    Err(e) => {
+                        error!("Failed to accept QUIC connection: {}", e);
+                        return;
+                    }
+                };
+                let new_conn = match connecting.into_0rtt() {
+                    Ok((new_conn, accepted)) => Ok((new_conn, Some(accepted))),
+                    Err(connecting) => connecting.await.map(|new_conn| (new_conn, None)),
+                };
+                match new_conn {
+                    Ok((new_conn, accepted)) => {
+                        let remote_addr = new_conn.remote_address().to_string();
+                        match QuicConnection::new(new_conn, remote_addr).await {
+                            Ok(mut conn) => {
+                                if let Some(accepted) = accepted {
+                                    conn = conn.with_zero_rtt(accepted);
+                                }
                                 let _ = server.add_connection(Box::new(conn)).await;
                             }
                             Err(e) => error!("Failed to create QUIC connection: {}", e),
@@ -148,19 +333,68 @@ where
 
         Ok(())
     }
+
+    /// 运行 TCP 服务器
+    async fn run_tcp_server(&self, addr: &str) -> Result<()> {
+        let tcp_addr = addr.parse::<SocketAddr>()
+            .map_err(|e| FlareErr::ConnectionError(format!("Invalid TCP address: {}", e)))?;
+        let listener = TcpListener::bind(&tcp_addr).await
+            .map_err(|e| FlareErr::ConnectionError(format!("Failed to bind TCP: {}", e)))?;
+
+        info!("TCP server listening on {}", tcp_addr);
+
+        let server = self.server.clone();
+
+        loop {
+            if let Ok((stream, addr)) = listener.accept().await {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    server.add_connection(Box::new(TcpConnection::new(stream, addr.to_string()))).await;
+                });
+            }
+        }
+    }
+}
+
+/// 运行 Prometheus 指标导出端点
+#[cfg(feature = "metrics")]
+async fn run_metrics_server(addr: &str) -> Result<()> {
+    let addr = addr.parse::<SocketAddr>()
+        .map_err(|e| FlareErr::ConnectionError(format!("Invalid metrics address: {}", e)))?;
+    flare_core::metrics::serve(addr, std::future::pending()).await
 }
 
+/// 等待停机信号，不在 await 点之间持有 watch 的读锁
+async fn wait_shutdown(rx: &mut watch::Receiver<bool>) {
+    let _ = rx.wait_for(|stop| *stop).await;
+}
+
+/// 服务器构建器
+///
+/// WebSocket、QUIC 和 TCP 都是可选的，至少配置一个监听；
+/// `ws_addr`、`quic_addr`、`tcp_addr` 可多次调用以添加多个监听，`quic_addr` 添加的监听共用同一套证书配置。
 pub struct FlareServerBuilder<S, A, Y>
 where
     S: ServerHandler + Send + Sync + 'static,
     A: AuthHandler + Send + Sync + 'static,
     Y: SystemHandler + Send + Sync + 'static,
 {
-    ws_addr: Option<String>,
-    quic_addr: Option<String>,
+    ws_listeners: Vec<WsListener>,
+    quic_listeners: Vec<QuicListener>,
+    quic_addrs: Vec<String>,
     quic_server_name: Option<String>,
     quic_cert_path: Option<String>,
     quic_key_path: Option<String>,
+    tcp_addrs: Vec<String>,
+    #[cfg(feature = "admin")]
+    admin_addr: Option<String>,
+    #[cfg(feature = "admin")]
+    admin_token: Option<String>,
+    #[cfg(feature = "metrics")]
+    metrics_addr: Option<String>,
+    drain_timeout: Duration,
+    reconnect_after: Duration,
+    resume_window: Option<Duration>,
     handle: Option<ServerMessageHandler<S, A, Y>>,
 }
 
@@ -172,22 +406,76 @@ where
 {
     pub fn new() -> Self {
         Self {
-            ws_addr: None,
-            quic_addr: None,
+            ws_listeners: Vec::new(),
+            quic_listeners: Vec::new(),
+            quic_addrs: Vec::new(),
             quic_server_name: None,
             quic_cert_path: None,
             quic_key_path: None,
+            tcp_addrs: Vec::new(),
+            #[cfg(feature = "admin")]
+            admin_addr: None,
+            #[cfg(feature = "admin")]
+            admin_token: None,
+            #[cfg(feature = "metrics")]
+            metrics_addr: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            reconnect_after: Duration::ZERO,
+            resume_window: None,
             handle: None,
         }
     }
 
+    /// 添加 WebSocket 监听
     pub fn ws_addr(mut self, addr: impl Into<String>) -> Self {
-        self.ws_addr = Some(addr.into());
+        self.ws_listeners.push(WsListener::new(addr));
         self
     }
 
+    /// 添加 WebSocket 监听，可通过 `WsListener::with_tls` 启用 wss
+    pub fn ws_listener(mut self, listener: WsListener) -> Self {
+        self.ws_listeners.push(listener);
+        self
+    }
+
+    /// 添加使用公共证书配置的 QUIC 监听
     pub fn quic_addr(mut self, addr: impl Into<String>) -> Self {
-        self.quic_addr = Some(addr.into());
+        self.quic_addrs.push(addr.into());
+        self
+    }
+
+    /// 添加使用独立证书配置的 QUIC 监听
+    pub fn quic_listener(mut self, listener: QuicListener) -> Self {
+        self.quic_listeners.push(listener);
+        self
+    }
+
+    /// 添加 TCP 监听，帧格式与 QUIC 相同
+    pub fn tcp_addr(mut self, addr: impl Into<String>) -> Self {
+        self.tcp_addrs.push(addr.into());
+        self
+    }
+
+    /// 在 `addr` 上提供 gRPC 网关管理服务
+    ///
+    /// 未设置 `admin_token` 时只允许监听本机回环地址
+    #[cfg(feature = "admin")]
+    pub fn admin_addr(mut self, addr: impl Into<String>) -> Self {
+        self.admin_addr = Some(addr.into());
+        self
+    }
+
+    /// 管理服务的访问令牌，请求需携带 `authorization: Bearer <token>` 元数据
+    #[cfg(feature = "admin")]
+    pub fn admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// 在 `addr` 上以 `GET /metrics` 导出 Prometheus 指标
+    #[cfg(feature = "metrics")]
+    pub fn metrics_addr(mut self, addr: impl Into<String>) -> Self {
+        self.metrics_addr = Some(addr.into());
         self
     }
 
@@ -206,6 +494,26 @@ where
         self
     }
 
+    /// 停机时等待处理中消息的最长时间
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// 停机通知中建议客户端的重连延迟，默认立即重连
+    ///
+    /// 滚动发布时设置为新节点就绪所需的时间，避免客户端集中重连
+    pub fn reconnect_after(mut self, delay: Duration) -> Self {
+        self.reconnect_after = delay;
+        self
+    }
+
+    /// 开启会话恢复，断线后在窗口内重连无需重新认证
+    pub fn resume_window(mut self, window: Duration) -> Self {
+        self.resume_window = Some(window);
+        self
+    }
+
     pub fn handler(mut self, handler: ServerMessageHandler<S, A, Y>) -> Self {
         self.handle = Some(handler);
         self
@@ -214,15 +522,39 @@ where
 
     pub fn build(self) -> Result<FlareServer<S, A, Y>> {
         let handler = self.handle.ok_or_else(|| anyhow::anyhow!("Handler is required"))?;
-        let server = Server::new(handler);
-        
+
+        let mut quic_listeners = self.quic_listeners;
+        if !self.quic_addrs.is_empty() {
+            let server_name = self.quic_server_name.ok_or_else(|| anyhow::anyhow!("QUIC server name is required"))?;
+            let cert_path = self.quic_cert_path.ok_or_else(|| anyhow::anyhow!("QUIC certificate path is required"))?;
+            let key_path = self.quic_key_path.ok_or_else(|| anyhow::anyhow!("QUIC key path is required"))?;
+            for addr in self.quic_addrs {
+                quic_listeners.push(QuicListener::new(addr, server_name.clone(), cert_path.clone(), key_path.clone()));
+            }
+        }
+        if self.ws_listeners.is_empty() && quic_listeners.is_empty() && self.tcp_addrs.is_empty() {
+            return Err(anyhow::anyhow!("At least one WebSocket, QUIC or TCP address is required").into());
+        }
+
+        let mut server = Server::new(handler);
+        if let Some(window) = self.resume_window {
+            server = server.with_resume_window(window);
+        }
+
         Ok(FlareServer {
             server: Arc::new(server),
-            ws_addr: self.ws_addr.ok_or_else(|| anyhow::anyhow!("WebSocket address is required"))?,
-            quic_addr: self.quic_addr.ok_or_else(|| anyhow::anyhow!("QUIC address is required"))?,
-            quic_server_name: self.quic_server_name.ok_or_else(|| anyhow::anyhow!("QUIC server name is required"))?,
-            quic_cert_path: self.quic_cert_path.ok_or_else(|| anyhow::anyhow!("QUIC certificate path is required"))?,
-            quic_key_path: self.quic_key_path.ok_or_else(|| anyhow::anyhow!("QUIC key path is required"))?,
+            ws_listeners: self.ws_listeners,
+            quic_listeners,
+            tcp_addrs: self.tcp_addrs,
+            #[cfg(feature = "admin")]
+            admin_addr: self.admin_addr,
+            #[cfg(feature = "admin")]
+            admin_token: self.admin_token,
+            #[cfg(feature = "metrics")]
+            metrics_addr: self.metrics_addr,
+            drain_timeout: self.drain_timeout,
+            reconnect_after: self.reconnect_after,
+            shutdown: Arc::new(watch::channel(false).0),
         })
     }
 }
@@ -236,4 +568,35 @@ where
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::auth_handler::DefAuthHandler;
+    use crate::server::server_handler::DefServerHandler;
+    use crate::server::sys_handler::DefSystemHandler;
+
+    type Builder = FlareServerBuilder<DefServerHandler, DefAuthHandler, DefSystemHandler>;
+
+    #[tokio::test]
+    async fn test_optional_transports() {
+        assert!(Builder::new().handler(ServerMessageHandler::default()).build().is_err());
+        // QUIC 监听缺少证书配置
+        assert!(Builder::new().handler(ServerMessageHandler::default()).quic_addr("127.0.0.1:0").build().is_err());
+
+        // 只启用 WebSocket，QUIC 证书不存在的监听失败后 WebSocket 继续服务
+        let server = Builder::new()
+            .handler(ServerMessageHandler::default())
+            .ws_addr("127.0.0.1:0")
+            .quic_listener(QuicListener::new("127.0.0.1:0", "localhost", "missing-cert.pem", "missing-key.pem"))
+            .build()
+            .unwrap();
+        let handle = server.shutdown_handle();
+        let run = tokio::spawn(async move { server.run().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!run.is_finished());
+        handle.shutdown();
+        assert!(tokio::time::timeout(Duration::from_secs(1), run).await.unwrap().unwrap().is_ok());
+    }
+}
@@ -1,4 +1,6 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::compile_protos("proto/echo.proto")?;
+    tonic_build::compile_protos("proto/gateway.proto")?;
+    tonic_build::compile_protos("proto/admin.proto")?;
     Ok(())
-} 
\ No newline at end of file
+} 
@@ -0,0 +1,15 @@
+#[cfg(feature = "server")]
+mod server;
+
+/// 生成的网关管理 proto 代码
+pub mod proto {
+    tonic::include_proto!("admin");
+}
+
+#[cfg(feature = "server")]
+pub use server::{AdminHandler, GatewayAdminService};
+#[cfg(feature = "server")]
+pub use proto::gateway_admin_server::GatewayAdminServer;
+
+#[cfg(feature = "client")]
+pub use proto::gateway_admin_client::GatewayAdminClient;
@@ -0,0 +1,138 @@
+use crate::admin::proto::gateway_admin_server::{GatewayAdmin, GatewayAdminServer};
+use crate::admin::proto::{
+    ConnectionList, KickRequest, KickResponse, ListConnectionsRequest, PushMessageRequest,
+    PushMessageResponse, StatsRequest, StatsResponse, UserSessionsRequest, UserSessionsResponse,
+};
+use async_trait::async_trait;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::Message;
+use log::info;
+use prost::Message as ProstMessage;
+use std::future::Future;
+use std::net::SocketAddr;
+use tonic::{Request, Response, Status};
+
+/// 携带管理令牌的元数据键
+const AUTHORIZATION: &str = "authorization";
+
+/// 管理处理器，由 IM 网关实现
+#[async_trait]
+pub trait AdminHandler: Send + Sync + 'static {
+    /// 分页列出连接，`limit` 为 0 时不限制
+    async fn list_connections(&self, offset: usize, limit: usize) -> Result<ConnectionList>;
+    /// 查询用户的会话
+    async fn user_sessions(&self, user_id: &str) -> Result<UserSessionsResponse>;
+    /// 踢下线用户在本节点的连接，`conn_id` 为 None 时踢掉所有连接，返回被踢的连接数
+    async fn kick(&self, user_id: &str, conn_id: Option<&str>, reason: &str) -> Result<u32>;
+    /// 推送消息给用户
+    async fn push_message(&self, user_id: &str, msg: Message) -> Result<()>;
+    /// 连接统计
+    async fn stats(&self) -> Result<StatsResponse>;
+}
+
+/// 网关管理 gRPC 服务
+///
+/// 未设置 `with_token` 时不校验调用方，只应监听本机回环地址
+pub struct GatewayAdminService<H: AdminHandler> {
+    handler: H,
+    token: Option<String>,
+}
+
+impl<H: AdminHandler> GatewayAdminService<H> {
+    pub fn new(handler: H) -> Self {
+        Self { handler, token: None }
+    }
+
+    /// 要求请求携带 `authorization: Bearer <token>` 元数据
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn authorize<T>(&self, request: &Request<T>) -> std::result::Result<(), Status> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+        let bearer = request.metadata().get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if bearer != Some(token.as_str()) {
+            return Err(Status::unauthenticated("invalid admin token"));
+        }
+        Ok(())
+    }
+
+    /// 转换为 tonic 服务，可直接添加到 `App::run` 的 server 中
+    pub fn into_server(self) -> GatewayAdminServer<Self> {
+        GatewayAdminServer::new(self)
+    }
+
+    /// 在 `addr` 上单独提供管理服务，`signal` 完成时退出
+    pub async fn serve(self, addr: SocketAddr, signal: impl Future<Output = ()>) -> Result<()> {
+        info!("Gateway admin service listening on {}", addr);
+        tonic::transport::Server::builder()
+            .add_service(self.into_server())
+            .serve_with_shutdown(addr, signal)
+            .await
+            .map_err(|e| FlareErr::connection_error(e.to_string()))
+    }
+}
+
+fn status(e: FlareErr) -> Status {
+    match e {
+        FlareErr::InvalidParams(msg) | FlareErr::ArgsError(msg) => Status::invalid_argument(msg),
+        FlareErr::ConnectionNotFound => Status::not_found(e.to_string()),
+        e => Status::internal(e.to_string()),
+    }
+}
+
+#[tonic::async_trait]
+impl<H: AdminHandler> GatewayAdmin for GatewayAdminService<H> {
+    async fn list_connections(&self, request: Request<ListConnectionsRequest>) -> std::result::Result<Response<ConnectionList>, Status> {
+        self.authorize(&request)?;
+        let req = request.into_inner();
+        let list = self.handler.list_connections(req.offset as usize, req.limit as usize).await.map_err(status)?;
+        Ok(Response::new(list))
+    }
+
+    async fn user_sessions(&self, request: Request<UserSessionsRequest>) -> std::result::Result<Response<UserSessionsResponse>, Status> {
+        self.authorize(&request)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("user_id is empty"));
+        }
+        let sessions = self.handler.user_sessions(&req.user_id).await.map_err(status)?;
+        Ok(Response::new(sessions))
+    }
+
+    async fn kick(&self, request: Request<KickRequest>) -> std::result::Result<Response<KickResponse>, Status> {
+        self.authorize(&request)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("user_id is empty"));
+        }
+        let conn_id = (!req.conn_id.is_empty()).then_some(req.conn_id.as_str());
+        info!("Admin kick user {} connection {:?}", req.user_id, conn_id);
+        let kicked = self.handler.kick(&req.user_id, conn_id, &req.reason).await.map_err(status)?;
+        Ok(Response::new(KickResponse { kicked }))
+    }
+
+    async fn push_message(&self, request: Request<PushMessageRequest>) -> std::result::Result<Response<PushMessageResponse>, Status> {
+        self.authorize(&request)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("user_id is empty"));
+        }
+        let msg = Message::decode(&req.message[..])
+            .map_err(|e| Status::invalid_argument(format!("Invalid message: {}", e)))?;
+        self.handler.push_message(&req.user_id, msg).await.map_err(status)?;
+        Ok(Response::new(PushMessageResponse {}))
+    }
+
+    async fn stats(&self, request: Request<StatsRequest>) -> std::result::Result<Response<StatsResponse>, Status> {
+        self.authorize(&request)?;
+        let stats = self.handler.stats().await.map_err(status)?;
+        Ok(Response::new(stats))
+    }
+}
@@ -21,6 +21,9 @@ where
     pub config: AppConfig,
     /// 服务注册器
     register: Option<R>,
+    /// 指标导出地址
+    #[cfg(feature = "metrics")]
+    metrics_addr: Option<SocketAddr>,
 }
 
 impl<R> App<R>
@@ -42,6 +45,8 @@ where
                 ..Default::default()
             },
             register: None,
+            #[cfg(feature = "metrics")]
+            metrics_addr: None,
         }
     }
 
@@ -53,6 +58,8 @@ where
                 ..Default::default()
             },
             register: None,
+            #[cfg(feature = "metrics")]
+            metrics_addr: None,
         }
     }
 
@@ -75,6 +82,13 @@ where
         self.register = Some(register);
         self
     }
+
+    /// 在 `addr` 上以 `GET /metrics` 导出 Prometheus 指标，RPC 调用指标需在服务端添加 `MetricsLayer`
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics_addr(mut self, addr: SocketAddr) -> Self {
+        self.metrics_addr = Some(addr);
+        self
+    }
     
 
     /// 添加应用标签
@@ -140,6 +154,14 @@ where
             }
         });
 
+        // 启动指标导出端点
+        #[cfg(feature = "metrics")]
+        let metrics_handle = self.metrics_addr.map(|addr| tokio::spawn(async move {
+            if let Err(e) = flare_core::metrics::serve(addr, std::future::pending()).await {
+                error!("Metrics endpoint error: {}", e);
+            }
+        }));
+
         // 启动服务器
         let addr: SocketAddr = format!("{}:{}", ip, port).parse()?;
         let server = Server::builder();
@@ -167,6 +189,10 @@ where
 
         // 停止心跳
         heartbeat_handle.abort();
+        #[cfg(feature = "metrics")]
+        if let Some(handle) = metrics_handle {
+            handle.abort();
+        }
 
         // 注销服务
         deregister_server(register, self.config.id).await?;
@@ -212,6 +238,8 @@ where
     tags: Vec<String>,
     metadata: HashMap<String, String>,
     register: Option<R>,
+    #[cfg(feature = "metrics")]
+    metrics_addr: Option<SocketAddr>,
 }
 
 impl<R> AppBuilder<R>
@@ -228,6 +256,8 @@ where
             tags: Vec::new(),
             metadata: HashMap::new(),
             register: None,
+            #[cfg(feature = "metrics")]
+            metrics_addr: None,
         }
     }
 
@@ -267,6 +297,13 @@ where
         self
     }
 
+    /// 设置指标导出地址
+    #[cfg(feature = "metrics")]
+    pub fn metrics_addr(mut self, addr: SocketAddr) -> Self {
+        self.metrics_addr = Some(addr);
+        self
+    }
+
     /// 构建 App 实例
     pub fn build(self) -> App<R> {
         App {
@@ -279,6 +316,8 @@ where
                 metadata: self.metadata,
             },
             register: self.register,
+            #[cfg(feature = "metrics")]
+            metrics_addr: self.metrics_addr,
         }
     }
 }
@@ -0,0 +1,76 @@
+use crate::client::{GrpcClient, RpcClient};
+use crate::discover::{RpcDiscovery, ServiceError};
+use crate::gateway::gateway_service_name;
+use crate::gateway::proto::gateway_push_client::GatewayPushClient;
+use crate::gateway::proto::PushRequest;
+use flare_core::error::{FlareErr, Result};
+use flare_core::flare_net::net::{Message, ResCode};
+use prost::Message as ProstMessage;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tonic::transport::Channel;
+
+impl GrpcClient for GatewayPushClient<Channel> {
+    fn new(channel: Channel) -> Self {
+        GatewayPushClient::new(channel)
+    }
+}
+
+/// 网关推送客户端
+///
+/// 通过服务发现定位目标网关节点，并缓存每个节点的连接
+#[derive(Clone)]
+pub struct GatewayPusher<D: RpcDiscovery> {
+    discovery: D,
+    clients: Arc<Mutex<HashMap<String, GatewayPushClient<Channel>>>>,
+}
+
+impl<D: RpcDiscovery> GatewayPusher<D> {
+    pub fn new(discovery: D) -> Self {
+        Self {
+            discovery,
+            clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 推送消息给指定节点上的用户
+    pub async fn push(&self, node_id: &str, user_id: &str, msg: Message) -> Result<()> {
+        let mut client = self.client(node_id).await?;
+        let request = PushRequest {
+            user_id: user_id.to_string(),
+            message: msg.encode_to_vec(),
+        };
+
+        let resp = match client.push_to_user(request).await {
+            Ok(resp) => resp.into_inner(),
+            Err(status) => {
+                // 连接可能已失效，下次重新发现
+                self.clients.lock().await.remove(node_id);
+                return Err(FlareErr::connection_error(status.to_string()));
+            }
+        };
+
+        if resp.code != ResCode::Success as i32 {
+            return Err(FlareErr::SendMsgErr(resp.code, resp.message));
+        }
+        Ok(())
+    }
+
+    async fn client(&self, node_id: &str) -> Result<GatewayPushClient<Channel>> {
+        if let Some(client) = self.clients.lock().await.get(node_id) {
+            return Ok(client.clone());
+        }
+
+        let factory = RpcClient::<GatewayPushClient<Channel>, D>::new(
+            gateway_service_name(node_id),
+            self.discovery.clone(),
+        );
+        let client = factory.client().await.map_err(|e| match e {
+            ServiceError::NotFound(name) => FlareErr::not_found_service(name),
+            e => FlareErr::connection_error(e.to_string()),
+        })?;
+        self.clients.lock().await.insert(node_id.to_string(), client.clone());
+        Ok(client)
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "client")]
+mod client;
+mod tests;
+
+/// 生成的网关推送 proto 代码
+pub mod proto {
+    tonic::include_proto!("gateway");
+}
+
+#[cfg(feature = "server")]
+pub use server::{GatewayPushService, PushHandler};
+#[cfg(feature = "server")]
+pub use proto::gateway_push_server::GatewayPushServer;
+
+#[cfg(feature = "client")]
+pub use client::GatewayPusher;
+#[cfg(feature = "client")]
+pub use proto::gateway_push_client::GatewayPushClient;
+
+/// 网关节点注册时使用的服务名前缀
+pub const GATEWAY_SERVICE_PREFIX: &str = "flare-gateway-";
+
+/// 网关节点在注册中心的服务名
+///
+/// 每个网关节点以该名称注册，推送时按节点ID定位到具体的网关
+pub fn gateway_service_name(node_id: &str) -> String {
+    format!("{}{}", GATEWAY_SERVICE_PREFIX, node_id)
+}
@@ -0,0 +1,52 @@
+use crate::gateway::proto::gateway_push_server::{GatewayPush, GatewayPushServer};
+use crate::gateway::proto::{PushRequest, PushResponse};
+use async_trait::async_trait;
+use flare_core::error::Result;
+use flare_core::flare_net::net::{Message, ResCode};
+use log::debug;
+use prost::Message as ProstMessage;
+use tonic::{Request, Response, Status};
+
+/// 推送处理器，由 IM 网关实现，把消息投递给本节点上的用户连接
+#[async_trait]
+pub trait PushHandler: Send + Sync + 'static {
+    async fn push_to_user(&self, user_id: &str, msg: Message) -> Result<()>;
+}
+
+/// 网关推送 gRPC 服务
+pub struct GatewayPushService<H: PushHandler> {
+    handler: H,
+}
+
+impl<H: PushHandler> GatewayPushService<H> {
+    pub fn new(handler: H) -> Self {
+        Self { handler }
+    }
+
+    /// 转换为 tonic 服务，可直接添加到 `App::run` 的 server 中
+    pub fn into_server(self) -> GatewayPushServer<Self> {
+        GatewayPushServer::new(self)
+    }
+}
+
+#[tonic::async_trait]
+impl<H: PushHandler> GatewayPush for GatewayPushService<H> {
+    async fn push_to_user(&self, request: Request<PushRequest>) -> std::result::Result<Response<PushResponse>, Status> {
+        let req = request.into_inner();
+        let msg = Message::decode(&req.message[..])
+            .map_err(|e| Status::invalid_argument(format!("Invalid message: {}", e)))?;
+        debug!("Gateway push to user {}: command={}", req.user_id, msg.command);
+
+        let resp = match self.handler.push_to_user(&req.user_id, msg).await {
+            Ok(()) => PushResponse {
+                code: ResCode::Success as i32,
+                message: String::new(),
+            },
+            Err(e) => PushResponse {
+                code: e.code() as i32,
+                message: e.to_string(),
+            },
+        };
+        Ok(Response::new(resp))
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use crate::discover::{RpcDiscovery, ServiceEndpoint, ServiceError};
+    use crate::gateway::{gateway_service_name, GatewayPushService, GatewayPusher, PushHandler};
+    use async_trait::async_trait;
+    use flare_core::error::{FlareErr, Result};
+    use flare_core::flare_net::net::{Command, Message};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use tonic::transport::server::TcpIncoming;
+
+    // 固定返回本地地址的服务发现
+    #[derive(Clone)]
+    struct StaticDiscovery {
+        service_name: String,
+        port: u16,
+    }
+
+    #[async_trait]
+    impl RpcDiscovery for StaticDiscovery {
+        async fn discover(&self, service_name: &str) -> std::result::Result<ServiceEndpoint, ServiceError> {
+            if service_name != self.service_name {
+                return Err(ServiceError::NotFound(service_name.to_string()));
+            }
+            Ok(ServiceEndpoint {
+                address: "127.0.0.1".to_string(),
+                port: self.port,
+                weight: 1,
+            })
+        }
+
+        async fn start_watch(&self) {}
+
+        async fn stop_watch(&self) {}
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordHandler {
+        pushed: Arc<Mutex<Vec<(String, Message)>>>,
+    }
+
+    #[async_trait]
+    impl PushHandler for RecordHandler {
+        async fn push_to_user(&self, user_id: &str, msg: Message) -> Result<()> {
+            if user_id.is_empty() {
+                return Err(FlareErr::invalid_params("user_id is empty"));
+            }
+            self.pushed.lock().await.push((user_id.to_string(), msg));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_push() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        // 1. 启动网关推送服务
+        let handler = RecordHandler::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let incoming = TcpIncoming::from_listener(listener, true, None).map_err(|e| e.to_string())?;
+        let server_future = tonic::transport::Server::builder()
+            .add_service(GatewayPushService::new(handler.clone()).into_server())
+            .serve_with_incoming(incoming);
+        tokio::spawn(server_future);
+        for _ in 0..50 {
+            if tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        // 2. 通过服务发现推送到 node-1
+        let pusher = GatewayPusher::new(StaticDiscovery {
+            service_name: gateway_service_name("node-1"),
+            port,
+        });
+        let msg = Message {
+            command: Command::ServerPushMsg as i32,
+            data: b"hello".to_vec(),
+            ..Default::default()
+        };
+        pusher.push("node-1", "user-1", msg.clone()).await?;
+
+        let pushed = handler.pushed.lock().await.clone();
+        assert_eq!(pushed, vec![("user-1".to_string(), msg.clone())]);
+
+        // 3. 处理器返回错误时透传错误码
+        assert!(matches!(pusher.push("node-1", "", msg.clone()).await, Err(FlareErr::SendMsgErr(_, _))));
+
+        // 4. 未注册的节点
+        assert!(matches!(pusher.push("node-2", "user-1", msg).await, Err(FlareErr::ServiceNotFound(_))));
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tonic::{Request, Status};
-use flare_core::context::{AppContext, AppContextBuilder};
+use flare_core::context::{AppContext, AppContextBuilder, TraceContext, TRACEPARENT_KEY};
 use tonic::metadata::{MetadataValue, MetadataMap, MetadataKey};
 use std::str::FromStr;
 
@@ -17,6 +17,7 @@ const VALUES_PREFIX: &str = "ctx-val-";
 #[cfg(feature = "client")]
 use {
     tower::{Service, Layer},
+    tracing::Instrument,
     std::future::Future,
     std::pin::Pin,
 };
@@ -105,10 +106,14 @@ where
             }
         }
 
+        let span = match metadata_trace(request.metadata()) {
+            Some(trace) => tracing::info_span!("rpc.client", trace_id = %trace.trace_id(), span_id = %trace.span_id()),
+            None => tracing::Span::none(),
+        };
         let mut inner = self.inner.clone();
         Box::pin(async move {
             inner.call(request).await
-        })
+        }.instrument(span))
     }
 }
 
@@ -154,6 +159,13 @@ pub fn build_req_metadata_form_ctx<B>(ctx: &AppContext, request: &mut Request<B>
         metadata.insert(CLIENT_MSG_ID_KEY, val);
     }
 
+    // 每次下游调用都是当前 span 的子 span
+    if let Some(trace) = ctx.trace() {
+        if let Ok(val) = MetadataValue::from_str(&trace.child().to_traceparent()) {
+            metadata.insert(TRACEPARENT_KEY, val);
+        }
+    }
+
     if let Ok(values) = ctx.values().lock() {
         for (key, value) in values.iter() {
             let metadata_key = format!("{}{}", VALUES_PREFIX, key);
@@ -232,6 +244,41 @@ pub fn build_context_from_metadata(metadata: &MetadataMap) -> Result<AppContext,
     }
     builder = builder.values(values);
 
+    // 格式错误的 traceparent 直接忽略，不影响调用
+    if let Some(trace) = metadata_trace(metadata) {
+        builder = builder.with_trace(trace);
+    }
+
     builder.build()
         .map_err(|e| Status::internal(format!("Failed to build AppContext: {}", e)))
 }
+
+/// 读取元数据中的 `traceparent`
+pub fn metadata_trace(metadata: &MetadataMap) -> Option<TraceContext> {
+    TraceContext::parse(metadata.get(TRACEPARENT_KEY)?.to_str().ok()?)
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_propagation() {
+        let trace = TraceContext::new_root();
+        let ctx = AppContextBuilder::new()
+            .remote_addr("127.0.0.1:12345".to_string())
+            .with_trace(trace)
+            .build()
+            .unwrap();
+        let mut request = Request::new(());
+        build_req_metadata_form_ctx(&ctx, &mut request);
+
+        // 下游拿到同一 trace 下的子 span
+        let downstream = build_context_from_metadata(request.metadata()).unwrap().trace().unwrap();
+        assert_eq!(downstream.trace_id(), trace.trace_id());
+        assert_ne!(downstream.span_id(), trace.span_id());
+
+        request.metadata_mut().insert(TRACEPARENT_KEY, MetadataValue::from_static("invalid"));
+        assert!(build_context_from_metadata(request.metadata()).unwrap().trace().is_none());
+    }
+}
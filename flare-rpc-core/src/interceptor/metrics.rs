@@ -0,0 +1,146 @@
+use flare_core::metrics::prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+use flare_core::metrics::LATENCY_BUCKETS;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::LazyLock;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tonic::codegen::http;
+use tonic::Code;
+use tower::{Layer, Service};
+
+static REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("flare_rpc_requests_total", "RPC calls", &["side", "method", "code"]).unwrap()
+});
+static LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "flare_rpc_request_duration_seconds",
+        "RPC call latency",
+        &["side", "method"],
+        LATENCY_BUCKETS.to_vec()
+    ).unwrap()
+});
+
+/// RPC 调用指标层，按方法统计调用次数、状态码和延迟
+///
+/// 服务端通过 `Server::builder().layer(MetricsLayer::server())` 添加，
+/// 客户端可包装 `Channel`
+#[derive(Debug, Clone, Copy)]
+pub struct MetricsLayer {
+    side: &'static str,
+}
+
+impl MetricsLayer {
+    pub fn server() -> Self {
+        Self { side: "server" }
+    }
+
+    pub fn client() -> Self {
+        Self { side: "client" }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService { inner, side: self.side }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    side: &'static str,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for MetricsService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        // gRPC 路径形如 /package.Service/Method
+        let method = request.uri().path().to_string();
+        let side = self.side;
+        let started = Instant::now();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let result = future.await;
+            let code = match &result {
+                // 只有 trailers-only 的错误响应在头部携带 grpc-status，其余视为成功
+                Ok(resp) => resp.headers().get("grpc-status")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<i32>().ok())
+                    .map(Code::from_i32)
+                    .unwrap_or(Code::Ok),
+                Err(_) => Code::Unavailable,
+            };
+            REQUESTS.with_label_values(&[side, &method, &format!("{:?}", code)]).inc();
+            LATENCY.with_label_values(&[side, &method]).observe(started.elapsed().as_secs_f64());
+            result
+        })
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::gateway::proto::gateway_push_client::GatewayPushClient;
+    use crate::gateway::proto::PushRequest;
+    use crate::gateway::{GatewayPushService, PushHandler};
+    use async_trait::async_trait;
+    use flare_core::error::{FlareErr, Result};
+    use flare_core::flare_net::net::Message;
+    use tonic::transport::Server;
+
+    struct RejectEmpty;
+
+    #[async_trait]
+    impl PushHandler for RejectEmpty {
+        async fn push_to_user(&self, user_id: &str, _msg: Message) -> Result<()> {
+            if user_id.is_empty() {
+                return Err(FlareErr::invalid_params("user_id is empty"));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_metrics_layer() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        tokio::spawn(Server::builder()
+            .layer(MetricsLayer::server())
+            .add_service(GatewayPushService::new(RejectEmpty).into_server())
+            .serve(addr));
+
+        let mut client = None;
+        for _ in 0..50 {
+            if let Ok(c) = GatewayPushClient::connect(format!("http://{}", addr)).await {
+                client = Some(c);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let mut client = client.expect("gateway push service");
+        client.push_to_user(PushRequest { user_id: "u1".into(), message: Vec::new() }).await.unwrap();
+        assert!(client.push_to_user(PushRequest { user_id: "u1".into(), message: vec![0xff] }).await.is_err());
+
+        let method = "/gateway.GatewayPush/PushToUser";
+        assert_eq!(REQUESTS.with_label_values(&["server", method, "Ok"]).get(), 1);
+        assert_eq!(REQUESTS.with_label_values(&["server", method, "InvalidArgument"]).get(), 1);
+        assert_eq!(LATENCY.with_label_values(&["server", method]).get_sample_count(), 2);
+    }
+}
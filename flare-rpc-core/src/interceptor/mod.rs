@@ -1,7 +1,19 @@
 pub mod ctxinterceprot;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod trace;
+
+pub use ctxinterceprot::metadata_trace;
 
 #[cfg(feature = "client")]
 pub use ctxinterceprot::{AppContextInterceptor, AppContextLayer, AppContextConfig, build_req_metadata_form_ctx};
 
 #[cfg(feature = "server")]
 pub use ctxinterceprot::build_context_from_metadata;
+
+#[cfg(feature = "server")]
+pub use trace::{TraceLayer, TraceService};
+
+#[cfg(feature = "metrics")]
+pub use metrics::{MetricsLayer, MetricsService};
@@ -0,0 +1,61 @@
+use flare_core::context::{TraceContext, TRACEPARENT_KEY};
+use std::task::{Context, Poll};
+use tonic::codegen::http;
+use tower::{Layer, Service};
+use tracing::instrument::Instrumented;
+use tracing::Instrument;
+
+/// 服务端链路追踪层，为每次调用创建 `rpc.server` span
+///
+/// 通过 `Server::builder().layer(TraceLayer)` 添加，请求携带 `traceparent` 时
+/// span 以 `trace_id`、`span_id` 字段记录调用方的上下文，用于日志关联；
+/// 开启 `opentelemetry` feature 后同时设为 span 的 OpenTelemetry 父上下文
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceLayer;
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = TraceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for TraceService<S>
+where
+    S: Service<http::Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Instrumented<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let trace = request.headers().get(TRACEPARENT_KEY)
+            .and_then(|v| v.to_str().ok())
+            .and_then(TraceContext::parse);
+        let span = match trace {
+            Some(trace) => {
+                let span = tracing::info_span!(
+                    "rpc.server",
+                    method = %request.uri().path(),
+                    trace_id = %trace.trace_id(),
+                    span_id = %trace.span_id(),
+                );
+                #[cfg(feature = "opentelemetry")]
+                trace.set_parent_of(&span);
+                span
+            }
+            None => tracing::info_span!("rpc.server", method = %request.uri().path()),
+        };
+        self.inner.call(request).instrument(span)
+    }
+}
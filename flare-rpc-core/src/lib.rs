@@ -7,6 +7,12 @@ pub mod app;
 pub mod discover;
 pub mod interceptor;
 
+#[cfg(any(feature = "client", feature = "server"))]
+pub mod gateway;
+
+#[cfg(any(feature = "client", feature = "server"))]
+pub mod admin;
+
 #[cfg(feature = "etcd")]
 extern crate etcd_client;
 